@@ -0,0 +1,44 @@
+use criterion::{black_box, criterion_group, criterion_main, Criterion};
+use fuels::types::{AssetId, ContractId};
+use fuels_amm_rs::amm::{mira::MiraV1, price::calculate_prices_batch, AutomatedMarketMaker, AMM};
+
+fn volatile_pools(count: u64) -> Vec<AMM> {
+    let token_0 = AssetId::new([1; 32]);
+    let token_1 = AssetId::new([2; 32]);
+    (1..=count)
+        .map(|i| {
+            AMM::MiraV1(MiraV1::new(
+                ContractId::zeroed(),
+                (token_0, token_1, false),
+                token_0,
+                9,
+                token_1,
+                9,
+                1_000_000_000 + i,
+                2_000_000_000 + i * 7,
+                (30, 5, 0, 0),
+                false,
+            ))
+        })
+        .collect()
+}
+
+fn bench_prices(c: &mut Criterion) {
+    let amms = volatile_pools(10_000);
+    let bases: Vec<AssetId> = amms.iter().map(|amm| amm.tokens()[0]).collect();
+
+    c.bench_function("calculate_price", |b| {
+        b.iter(|| {
+            amms.iter()
+                .zip(&bases)
+                .map(|(amm, base)| amm.calculate_price(*base, amm.get_token_out(*base)))
+                .collect::<Vec<_>>()
+        })
+    });
+    c.bench_function("calculate_prices_batch", |b| {
+        b.iter(|| calculate_prices_batch(black_box(&amms), black_box(&bases)))
+    });
+}
+
+criterion_group!(benches, bench_prices);
+criterion_main!(benches);
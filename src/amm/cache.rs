@@ -0,0 +1,111 @@
+use std::collections::HashMap;
+
+use fuels::types::{AssetId, U256};
+
+use super::{AutomatedMarketMaker, PoolKey, AMM};
+use crate::errors::SwapSimulationError;
+
+/// Memoizes swap simulations between syncs.
+///
+/// Entries are keyed by pool and dropped as soon as the pool's reserves change.
+#[derive(Debug, Default)]
+pub struct SwapCache {
+    pools: HashMap<PoolKey, CachedPool>,
+}
+
+#[derive(Debug)]
+struct CachedPool {
+    reserves: (u64, u64),
+    outputs: HashMap<(AssetId, U256), U256>,
+}
+
+impl SwapCache {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Simulates a swap of `amount_in` of `token_in`, reusing the cached output when the pool's
+    /// reserves have not changed.
+    pub fn simulate_swap(
+        &mut self,
+        amm: &AMM,
+        token_in: AssetId,
+        amount_in: U256,
+    ) -> Result<U256, SwapSimulationError> {
+        let reserves = amm.reserves();
+        let pool = self
+            .pools
+            .entry(amm.pool_key())
+            .or_insert_with(|| CachedPool {
+                reserves,
+                outputs: HashMap::new(),
+            });
+        if pool.reserves != reserves {
+            pool.reserves = reserves;
+            pool.outputs.clear();
+        }
+
+        if let Some(amount_out) = pool.outputs.get(&(token_in, amount_in)) {
+            return Ok(*amount_out);
+        }
+
+        let amount_out = amm.simulate_swap(token_in, amm.get_token_out(token_in), amount_in)?;
+        pool.outputs.insert((token_in, amount_in), amount_out);
+        Ok(amount_out)
+    }
+
+    /// Returns the number of cached outputs.
+    pub fn len(&self) -> usize {
+        self.pools.values().map(|pool| pool.outputs.len()).sum()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    pub fn clear(&mut self) {
+        self.pools.clear();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use fuels::types::{AssetId, ContractId, U256};
+
+    use super::SwapCache;
+    use crate::amm::{oxiswap::Oxiswap, AutomatedMarketMaker, AMM};
+
+    #[test]
+    fn test_swap_cache() {
+        let token_a = AssetId::new([1; 32]);
+        let token_b = AssetId::new([2; 32]);
+        let mut pool = Oxiswap::new(
+            ContractId::zeroed(),
+            token_a,
+            token_b,
+            1_000_000,
+            1_000_000,
+            300,
+        );
+        let mut cache = SwapCache::new();
+        let amount_in = U256::from(10_000);
+
+        let amm = AMM::Oxiswap(pool);
+        let amount_out = cache.simulate_swap(&amm, token_a, amount_in).unwrap();
+        assert_eq!(
+            cache.simulate_swap(&amm, token_a, amount_in).unwrap(),
+            amount_out
+        );
+        assert_eq!(cache.len(), 1);
+
+        pool.reserve_b = 2_000_000;
+        let amm = AMM::Oxiswap(pool);
+        let amount_out_after_sync = cache.simulate_swap(&amm, token_a, amount_in).unwrap();
+        assert_eq!(
+            amount_out_after_sync,
+            amm.simulate_swap(token_a, token_b, amount_in).unwrap()
+        );
+        assert_ne!(amount_out_after_sync, amount_out);
+        assert_eq!(cache.len(), 1);
+    }
+}
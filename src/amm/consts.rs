@@ -1 +1,14 @@
 pub const U128_0X10000000000000000: u128 = 18446744073709551616;
+
+// Decimals of native Fuel assets, assumed for pools whose token decimals are not known
+pub const FUEL_DEFAULT_DECIMALS: u8 = 9;
+
+// Default number of pools read per call when enumerating a factory
+pub const DEFAULT_ENUMERATION_STEP: u64 = 766;
+
+// Default population tuning for the Mira factory
+pub const MIRA_FACTORY_DEFAULT_MAX_CONCURRENCY: usize = 8;
+pub const MIRA_FACTORY_DEFAULT_CHUNK_SIZE: usize = 100;
+
+// Highest pool fee accepted when reading pool state, in basis points
+pub const DEFAULT_MAX_FEE_BPS: u32 = 1000;
@@ -1,9 +1,17 @@
 use async_trait::async_trait;
-use fuels::{accounts::wallet::Wallet, types::ContractId};
+use fuels::{
+    accounts::wallet::Wallet,
+    types::{AssetId, ContractId},
+};
+use futures::{stream, StreamExt, TryStreamExt};
+use serde::{Deserialize, Serialize};
 
 use crate::errors::AMMError;
 
-use super::AMM;
+use super::{
+    consts::DEFAULT_ENUMERATION_STEP, dedup_amms, filter_amms_with_empty_reserves,
+    mira::factory::MiraFactory, AutomatedMarketMaker, AMM,
+};
 #[async_trait]
 pub trait AutomatedMarketMakerFactory {
     /// Returns the address of the factory.
@@ -26,4 +34,518 @@ pub trait AutomatedMarketMakerFactory {
         block_number: Option<u64>,
         wallet: Wallet,
     ) -> Result<(), AMMError>;
+
+    /// Populates all AMMs data, collecting per-pool failures instead of aborting the batch.
+    ///
+    /// The pools are updated only once every call has completed, so dropping the future leaves
+    /// `amms` untouched. Returns the address and error of every pool that failed to populate.
+    async fn populate_amm_data_partial(
+        &self,
+        amms: &mut [AMM],
+        block_number: Option<u64>,
+        wallet: Wallet,
+    ) -> Result<Vec<(ContractId, AMMError)>, AMMError> {
+        let mut staged = amms.to_vec();
+        let mut failures = vec![];
+        for amm in staged.iter_mut() {
+            if let Err(err) = amm.populate_data(block_number, wallet.clone()).await {
+                failures.push((amm.address(), err));
+            }
+        }
+
+        amms.clone_from_slice(&staged);
+        Ok(failures)
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub enum Factory {
+    MiraV1(MiraFactory),
+}
+
+#[async_trait]
+impl AutomatedMarketMakerFactory for Factory {
+    fn address(&self) -> ContractId {
+        match self {
+            Factory::MiraV1(factory) => factory.address(),
+        }
+    }
+
+    async fn get_all_amms(
+        &self,
+        to_block: Option<u64>,
+        wallet: Wallet,
+        step: u64,
+    ) -> Result<Vec<AMM>, AMMError> {
+        match self {
+            Factory::MiraV1(factory) => factory.get_all_amms(to_block, wallet, step).await,
+        }
+    }
+
+    async fn populate_amm_data(
+        &self,
+        amms: &mut [AMM],
+        block_number: Option<u64>,
+        wallet: Wallet,
+    ) -> Result<(), AMMError> {
+        match self {
+            Factory::MiraV1(factory) => factory.populate_amm_data(amms, block_number, wallet).await,
+        }
+    }
+}
+
+/// Gets all pools from every factory, merged into one list without duplicates.
+///
+/// Pools are deduplicated by [`PoolKey`](super::PoolKey), keeping the first factory's copy.
+#[cfg_attr(
+    feature = "tracing",
+    tracing::instrument(
+        level = "debug",
+        skip_all,
+        fields(factories = factories.len(), step = step)
+    )
+)]
+pub async fn enumerate_all<F: AutomatedMarketMakerFactory + Sync>(
+    factories: &[F],
+    wallet: Wallet,
+    step: u64,
+) -> Result<Vec<AMM>, AMMError> {
+    #[cfg(feature = "tracing")]
+    let _elapsed = crate::telemetry::Elapsed::start();
+
+    let mut amms = vec![];
+    for factory in factories {
+        amms.extend(factory.get_all_amms(None, wallet.clone(), step).await?);
+    }
+
+    Ok(dedup_amms(amms))
+}
+
+/// Populates `amms` in batches of `chunk_size` pools, with at most `max_concurrency` calls in
+/// flight within a batch.
+///
+/// Like [`AutomatedMarketMakerFactory::populate_amm_data_partial`], the pools are updated only
+/// once every call has completed, so dropping the future leaves `amms` untouched.
+pub async fn populate_in_chunks(
+    amms: &mut [AMM],
+    block_number: Option<u64>,
+    wallet: Wallet,
+    chunk_size: usize,
+    max_concurrency: usize,
+) -> Result<(), AMMError> {
+    let mut staged = amms.to_vec();
+    for chunk in staged.chunks_mut(chunk_size.max(1)) {
+        stream::iter(chunk.iter_mut())
+            .map(|amm| amm.populate_data(block_number, wallet.clone()))
+            .buffer_unordered(max_concurrency.max(1))
+            .try_collect::<Vec<()>>()
+            .await?;
+    }
+
+    amms.clone_from_slice(&staged);
+    Ok(())
+}
+
+/// Gets and populates every pool of `factory`, keeping only those usable for routing.
+///
+/// Enumerates with [`DEFAULT_ENUMERATION_STEP`] and populates through the factory, then applies
+/// [`filter_usable_amms`].
+pub async fn load_usable_amms<F: AutomatedMarketMakerFactory + Sync>(
+    factory: &F,
+    wallet: Wallet,
+    blacklist: Vec<AssetId>,
+    min_tvl_token: AssetId,
+    min_tvl: u64,
+) -> Result<Vec<AMM>, AMMError> {
+    let mut amms = factory
+        .get_all_amms(None, wallet.clone(), DEFAULT_ENUMERATION_STEP)
+        .await?;
+    factory.populate_amm_data(&mut amms, None, wallet).await?;
+
+    Ok(filter_usable_amms(amms, &blacklist, min_tvl_token, min_tvl))
+}
+
+/// Keeps the pools of `amms` usable for routing.
+///
+/// Drops pools with an empty reserve, pools holding a `blacklist` token, and pools holding less
+/// than `min_tvl` of `min_tvl_token`. Pools without `min_tvl_token` cannot be valued and are
+/// dropped as well.
+pub fn filter_usable_amms(
+    amms: Vec<AMM>,
+    blacklist: &[AssetId],
+    min_tvl_token: AssetId,
+    min_tvl: u64,
+) -> Vec<AMM> {
+    let mut amms = filter_amms_with_empty_reserves(amms);
+    amms.retain(|amm| {
+        let tokens = amm.tokens();
+        let (reserve_0, reserve_1) = amm.reserves();
+        let tvl = if tokens.first() == Some(&min_tvl_token) {
+            reserve_0
+        } else if tokens.get(1) == Some(&min_tvl_token) {
+            reserve_1
+        } else {
+            return false;
+        };
+
+        tvl >= min_tvl && !tokens.iter().any(|token| blacklist.contains(token))
+    });
+    amms
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{future::Future, pin::pin, task::Context};
+
+    use async_trait::async_trait;
+    use fuels::{
+        accounts::wallet::Wallet,
+        types::{bech32::Bech32Address, AssetId, ContractId, U256},
+    };
+    use futures::task::noop_waker;
+
+    use super::{
+        enumerate_all, filter_usable_amms, load_usable_amms, populate_in_chunks,
+        AutomatedMarketMakerFactory,
+    };
+    use crate::{
+        amm::{oxiswap::Oxiswap, AutomatedMarketMaker, RawState, AMM},
+        errors::{AMMError, ArithmeticError, SwapSimulationError},
+    };
+
+    /// A pool whose data is populated locally, fails to populate when `fail` is set, or never
+    /// populates when `stall` is set.
+    #[derive(Debug, Clone)]
+    struct LocalPool {
+        address: ContractId,
+        reserves: (u64, u64),
+        fail: bool,
+        stall: bool,
+    }
+
+    #[async_trait]
+    impl AutomatedMarketMaker for LocalPool {
+        fn address(&self) -> ContractId {
+            self.address
+        }
+
+        async fn sync(&mut self, _wallet: Wallet) -> Result<(), AMMError> {
+            Ok(())
+        }
+
+        fn tokens(&self) -> Vec<AssetId> {
+            vec![]
+        }
+
+        fn calculate_price(
+            &self,
+            _base_token: AssetId,
+            _quote_token: AssetId,
+        ) -> Result<f64, ArithmeticError> {
+            Ok(1.0)
+        }
+
+        async fn populate_data(
+            &mut self,
+            _block_number: Option<u64>,
+            _wallet: Wallet,
+        ) -> Result<(), AMMError> {
+            if self.stall {
+                std::future::pending::<()>().await;
+            }
+            if self.fail {
+                return Err(AMMError::PoolNotFound);
+            }
+            self.reserves = (2_000, 2_000);
+            Ok(())
+        }
+
+        fn simulate_swap(
+            &self,
+            _base_token: AssetId,
+            _quote_token: AssetId,
+            amount_in: U256,
+        ) -> Result<U256, SwapSimulationError> {
+            Ok(amount_in)
+        }
+
+        fn simulate_swap_mut(
+            &mut self,
+            _base_token: AssetId,
+            _quote_token: AssetId,
+            amount_in: U256,
+        ) -> Result<U256, SwapSimulationError> {
+            Ok(amount_in)
+        }
+
+        fn get_token_out(&self, token_in: AssetId) -> AssetId {
+            token_in
+        }
+
+        fn supports_swap(&self, _token_in: AssetId, _token_out: AssetId) -> bool {
+            false
+        }
+
+        fn reserves(&self) -> (u64, u64) {
+            self.reserves
+        }
+
+        fn normalized_reserves(&self) -> (U256, U256) {
+            (U256::from(self.reserves.0), U256::from(self.reserves.1))
+        }
+
+        fn raw_state(&self) -> RawState {
+            RawState {
+                reserve_0: U256::from(self.reserves.0),
+                reserve_1: U256::from(self.reserves.1),
+                decimals_0: 18,
+                decimals_1: 18,
+                fee_bps: 0,
+                is_stable: false,
+            }
+        }
+
+        fn has_liquidity(&self) -> bool {
+            self.reserves.0 != 0 && self.reserves.1 != 0
+        }
+
+        fn lp_asset_id(&self) -> Option<AssetId> {
+            None
+        }
+    }
+
+    /// A factory enumerating a fixed list of pools.
+    #[derive(Default)]
+    struct LocalFactory {
+        amms: Vec<AMM>,
+    }
+
+    #[async_trait]
+    impl AutomatedMarketMakerFactory for LocalFactory {
+        fn address(&self) -> ContractId {
+            ContractId::zeroed()
+        }
+
+        async fn get_all_amms(
+            &self,
+            _to_block: Option<u64>,
+            _wallet: Wallet,
+            _step: u64,
+        ) -> Result<Vec<AMM>, AMMError> {
+            Ok(self.amms.clone())
+        }
+
+        async fn populate_amm_data(
+            &self,
+            _amms: &mut [AMM],
+            _block_number: Option<u64>,
+            _wallet: Wallet,
+        ) -> Result<(), AMMError> {
+            Ok(())
+        }
+    }
+
+    #[test]
+    fn test_populate_amm_data_partial_cancelled() {
+        let pool = |stall| {
+            AMM::Custom(Box::new(LocalPool {
+                address: ContractId::zeroed(),
+                reserves: (1_000, 1_000),
+                fail: false,
+                stall,
+            }))
+        };
+        let mut amms = vec![pool(false), pool(true)];
+        let wallet = Wallet::from_address(Bech32Address::default(), None);
+
+        let waker = noop_waker();
+        let mut cx = Context::from_waker(&waker);
+
+        {
+            let mut future = pin!(LocalFactory::default().populate_amm_data_partial(
+                &mut amms,
+                None,
+                wallet.clone()
+            ));
+            // The first pool is populated before the second one stalls, then the future is dropped.
+            assert!(future.as_mut().poll(&mut cx).is_pending());
+        }
+
+        assert_eq!(amms[0].reserves(), (1_000, 1_000));
+        assert_eq!(amms[1].reserves(), (1_000, 1_000));
+
+        {
+            let mut future = pin!(populate_in_chunks(&mut amms, None, wallet, 1, 1));
+            assert!(future.as_mut().poll(&mut cx).is_pending());
+        }
+
+        assert_eq!(amms[0].reserves(), (1_000, 1_000));
+        assert_eq!(amms[1].reserves(), (1_000, 1_000));
+    }
+
+    #[test]
+    fn test_populate_amm_data_partial_reports_failures() {
+        let pool = |address, fail| {
+            AMM::Custom(Box::new(LocalPool {
+                address: ContractId::new([address; 32]),
+                reserves: (1_000, 1_000),
+                fail,
+                stall: false,
+            }))
+        };
+        let mut amms = vec![pool(1, false), pool(2, true), pool(3, false)];
+        let wallet = Wallet::from_address(Bech32Address::default(), None);
+        let runtime = tokio::runtime::Builder::new_current_thread()
+            .build()
+            .unwrap();
+
+        let failures = runtime
+            .block_on(LocalFactory::default().populate_amm_data_partial(&mut amms, None, wallet))
+            .unwrap();
+        assert_eq!(failures.len(), 1);
+        assert_eq!(failures[0].0, ContractId::new([2; 32]));
+        assert!(matches!(failures[0].1, AMMError::PoolNotFound));
+
+        assert_eq!(amms[0].reserves(), (2_000, 2_000));
+        assert_eq!(amms[1].reserves(), (1_000, 1_000));
+        assert_eq!(amms[2].reserves(), (2_000, 2_000));
+    }
+
+    #[test]
+    fn test_enumerate_all() {
+        let pool = |address| {
+            AMM::Oxiswap(Oxiswap::new(
+                ContractId::new([address; 32]),
+                AssetId::new([1; 32]),
+                AssetId::new([2; 32]),
+                1_000_000,
+                1_000_000,
+                300,
+            ))
+        };
+        // Pool 2 is listed by both factories.
+        let factories = [
+            LocalFactory {
+                amms: vec![pool(1), pool(2)],
+            },
+            LocalFactory {
+                amms: vec![pool(2), pool(3)],
+            },
+        ];
+        let wallet = Wallet::from_address(Bech32Address::default(), None);
+        let runtime = tokio::runtime::Builder::new_current_thread()
+            .build()
+            .unwrap();
+
+        let amms = runtime
+            .block_on(enumerate_all(&factories, wallet.clone(), 100))
+            .unwrap();
+        let addresses: Vec<ContractId> = amms.iter().map(|amm| amm.address()).collect();
+        assert_eq!(
+            addresses,
+            vec![
+                ContractId::new([1; 32]),
+                ContractId::new([2; 32]),
+                ContractId::new([3; 32])
+            ]
+        );
+
+        let none: [LocalFactory; 0] = [];
+        assert!(runtime
+            .block_on(enumerate_all(&none, wallet, 100))
+            .unwrap()
+            .is_empty());
+    }
+
+    #[test]
+    fn test_populate_in_chunks() {
+        let mut amms: Vec<AMM> = (0..5)
+            .map(|_| {
+                AMM::Custom(Box::new(LocalPool {
+                    address: ContractId::zeroed(),
+                    reserves: (1_000, 1_000),
+                    fail: false,
+                    stall: false,
+                }))
+            })
+            .collect();
+        let wallet = Wallet::from_address(Bech32Address::default(), None);
+        let runtime = tokio::runtime::Builder::new_current_thread()
+            .build()
+            .unwrap();
+
+        runtime
+            .block_on(populate_in_chunks(&mut amms, None, wallet, 2, 2))
+            .unwrap();
+        assert!(amms.iter().all(|amm| amm.reserves() == (2_000, 2_000)));
+    }
+
+    #[test]
+    fn test_filter_usable_amms() {
+        let token = AssetId::new([1; 32]);
+        let base = AssetId::new([2; 32]);
+        let scam = AssetId::new([3; 32]);
+        let other = AssetId::new([4; 32]);
+        let pool = |token_a, token_b, reserve_a, reserve_b| {
+            AMM::Oxiswap(Oxiswap::new(
+                ContractId::zeroed(),
+                token_a,
+                token_b,
+                reserve_a,
+                reserve_b,
+                300,
+            ))
+        };
+        let amms = vec![
+            pool(token, base, 1_000_000, 1_000_000),
+            pool(token, base, 1_000_000, 0),
+            pool(base, scam, 1_000_000, 1_000_000),
+            pool(base, other, 2_000_000, 1_000_000),
+            pool(token, base, 1_000_000, 10),
+            pool(token, other, 1_000_000, 1_000_000),
+        ];
+
+        let amms = filter_usable_amms(amms, &[scam], base, 1_000);
+        let reserves: Vec<_> = amms.iter().map(|amm| amm.reserves()).collect();
+        assert_eq!(
+            reserves,
+            vec![(1_000_000, 1_000_000), (2_000_000, 1_000_000)]
+        );
+    }
+
+    #[test]
+    fn test_load_usable_amms() {
+        let token = AssetId::new([1; 32]);
+        let base = AssetId::new([2; 32]);
+        let scam = AssetId::new([3; 32]);
+        let pool = |address, token_a, token_b, reserve_b| {
+            AMM::Oxiswap(Oxiswap::new(
+                ContractId::new([address; 32]),
+                token_a,
+                token_b,
+                1_000_000,
+                reserve_b,
+                300,
+            ))
+        };
+        let factory = LocalFactory {
+            amms: vec![
+                pool(1, token, base, 1_000_000),
+                pool(2, token, base, 0),
+                pool(3, base, scam, 1_000_000),
+                pool(4, token, base, 10),
+            ],
+        };
+        let wallet = Wallet::from_address(Bech32Address::default(), None);
+        let runtime = tokio::runtime::Builder::new_current_thread()
+            .build()
+            .unwrap();
+
+        // The empty pool, the blacklisted pool and the pool below the minimum TVL are dropped.
+        let amms = runtime
+            .block_on(load_usable_amms(&factory, wallet, vec![scam], base, 1_000))
+            .unwrap();
+        let addresses: Vec<ContractId> = amms.iter().map(|amm| amm.address()).collect();
+        assert_eq!(addresses, vec![ContractId::new([1; 32])]);
+    }
 }
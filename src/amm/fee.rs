@@ -0,0 +1,263 @@
+use std::{fmt::Debug, ops::Add};
+
+use fuels::types::{AssetId, U256};
+
+use super::{mira::MiraV1, oxiswap::Oxiswap, AutomatedMarketMaker};
+use crate::errors::SwapSimulationError;
+
+/// A fee expressed in basis points, where 10_000 bps is 100%.
+///
+/// Pools store fees in raw units of a tenth of a basis point (a raw fee of 300 is 30 bps), and
+/// `Bps` keeps that precision, so converting a raw fee and back is lossless. Construct through
+/// [`Bps::from_raw`] or [`Bps::from_bps`] so the scaling is explicit. Swap math uses
+/// [`Bps::complement_raw`] over [`Bps::RAW_DENOMINATOR`] to charge sub-bps fees exactly.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct Bps(u32);
+
+impl Bps {
+    /// The number of basis points in 100%.
+    pub const DENOMINATOR: u32 = 10_000;
+
+    /// The number of tenths of a basis point in 100%.
+    pub const RAW_DENOMINATOR: u32 = 100_000;
+
+    /// Creates a fee from a value already expressed in basis points.
+    pub const fn from_bps(bps: u32) -> Self {
+        Bps(bps * 10)
+    }
+
+    /// Creates a fee from a raw on-chain fee, in tenths of a basis point.
+    pub const fn from_raw(raw: u64) -> Self {
+        Bps(raw as u32)
+    }
+
+    /// Creates a fee from a raw Mira fee, see [`Bps::from_raw`].
+    pub const fn from_raw_mira(raw: u64) -> Self {
+        Self::from_raw(raw)
+    }
+
+    /// Returns the fee in whole basis points, rounded down.
+    pub const fn as_bps(self) -> u32 {
+        self.0 / 10
+    }
+
+    /// Returns the fee as a raw on-chain fee, in tenths of a basis point.
+    pub const fn to_raw(self) -> u64 {
+        self.0 as u64
+    }
+
+    /// Returns the fee as a raw Mira fee, see [`Bps::to_raw`].
+    pub const fn to_raw_mira(self) -> u64 {
+        self.to_raw()
+    }
+
+    /// Returns the share of the input kept after the fee, in tenths of a basis point.
+    pub const fn complement_raw(self) -> u32 {
+        Self::RAW_DENOMINATOR.saturating_sub(self.0)
+    }
+
+    /// Returns the fee as a fraction of the input, e.g. `0.003` for 30 bps.
+    pub fn as_fraction(self) -> f64 {
+        self.0 as f64 / Self::RAW_DENOMINATOR as f64
+    }
+}
+
+impl Add for Bps {
+    type Output = Bps;
+
+    fn add(self, rhs: Bps) -> Bps {
+        Bps(self.0 + rhs.0)
+    }
+}
+
+/// Computes a swap fee from the pool state and the size of the trade.
+pub trait FeeModel: Debug + Send + Sync {
+    /// Returns the fee in basis points for swapping `amount_in` against the given reserves.
+    fn fee_bps(&self, reserve_in: U256, reserve_out: U256, amount_in: U256) -> u32;
+}
+
+/// Pools whose static swap fee can be replaced by a [`FeeModel`].
+pub trait DynamicFee: AutomatedMarketMaker + Sized {
+    /// Returns the reserves of `token_in` and of the other token.
+    fn reserves_for(&self, token_in: AssetId) -> (U256, U256);
+
+    /// Returns a copy of the pool charging `fee_bps` on swaps.
+    fn with_fee_bps(&self, fee_bps: u32) -> Self;
+}
+
+impl DynamicFee for Oxiswap {
+    fn reserves_for(&self, token_in: AssetId) -> (U256, U256) {
+        if self.token_a == token_in {
+            (U256::from(self.reserve_a), U256::from(self.reserve_b))
+        } else {
+            (U256::from(self.reserve_b), U256::from(self.reserve_a))
+        }
+    }
+
+    fn with_fee_bps(&self, fee_bps: u32) -> Self {
+        Oxiswap {
+            fee: Bps::from_bps(fee_bps).to_raw(),
+            ..*self
+        }
+    }
+}
+
+impl DynamicFee for MiraV1 {
+    fn reserves_for(&self, token_in: AssetId) -> (U256, U256) {
+        if self.token_0 == token_in {
+            (U256::from(self.reserve_0), U256::from(self.reserve_1))
+        } else {
+            (U256::from(self.reserve_1), U256::from(self.reserve_0))
+        }
+    }
+
+    fn with_fee_bps(&self, fee_bps: u32) -> Self {
+        let fee = Bps::from_bps(fee_bps).to_raw_mira();
+        MiraV1 {
+            fee: (fee, fee, 0, 0),
+            ..*self
+        }
+    }
+}
+
+/// A pool whose static fee is optionally overridden by a [`FeeModel`].
+#[derive(Debug)]
+pub struct DynamicFeePool<P> {
+    pub pool: P,
+    pub fee_model: Option<Box<dyn FeeModel>>,
+}
+
+impl<P: DynamicFee> DynamicFeePool<P> {
+    /// Creates a new pool wrapper, keeping the static fee when `fee_model` is `None`.
+    pub fn new(pool: P, fee_model: Option<Box<dyn FeeModel>>) -> Self {
+        Self { pool, fee_model }
+    }
+
+    /// Locally simulates a swap using the fee from the fee model.
+    ///
+    /// Returns the amount received for `amount_in` of `base_token`.
+    pub fn simulate_swap(
+        &self,
+        base_token: AssetId,
+        amount_in: U256,
+    ) -> Result<U256, SwapSimulationError> {
+        let quote_token = self.pool.get_token_out(base_token);
+        match &self.fee_model {
+            Some(fee_model) => {
+                let (reserve_in, reserve_out) = self.pool.reserves_for(base_token);
+                let fee_bps = fee_model.fee_bps(reserve_in, reserve_out, amount_in);
+                self.pool
+                    .with_fee_bps(fee_bps)
+                    .simulate_swap(base_token, quote_token, amount_in)
+            }
+            None => self.pool.simulate_swap(base_token, quote_token, amount_in),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use fuels::types::{AssetId, ContractId, U256};
+
+    use super::{Bps, DynamicFeePool, FeeModel};
+    use crate::amm::{oxiswap::Oxiswap, AutomatedMarketMaker};
+
+    /// Charges 30 bps, rising to 100 bps for trades above 1% of the input reserve.
+    #[derive(Debug)]
+    struct SizeFeeModel;
+
+    impl FeeModel for SizeFeeModel {
+        fn fee_bps(&self, reserve_in: U256, _reserve_out: U256, amount_in: U256) -> u32 {
+            if amount_in * U256::from(100) > reserve_in {
+                100
+            } else {
+                30
+            }
+        }
+    }
+
+    #[test]
+    fn test_bps_conversions() {
+        let fee = Bps::from_raw_mira(300);
+        assert_eq!(fee, Bps::from_bps(30));
+        assert_eq!(fee.as_bps(), 30);
+        assert_eq!(fee.to_raw_mira(), 300);
+        assert_eq!(fee.complement_raw(), 99_700);
+        assert_eq!(fee.as_fraction(), 0.003);
+
+        assert_eq!(Bps::from_raw(300), fee);
+        assert_eq!(Bps::from_bps(30).to_raw(), 300);
+
+        // Sub-bps fees survive the conversion, and only whole bps round down.
+        let fee = Bps::from_raw_mira(305);
+        assert!(fee > Bps::from_bps(30));
+        assert_eq!(fee.as_bps(), 30);
+        assert_eq!(fee.to_raw(), 305);
+        assert_eq!(fee.complement_raw(), 99_695);
+        assert_eq!(Bps::from_raw(5).as_fraction(), 0.00005);
+        assert_eq!(
+            Bps::from_raw_mira(300) + Bps::from_raw_mira(55),
+            Bps::from_raw(355)
+        );
+        assert_eq!(Bps::from_bps(12_000).complement_raw(), 0);
+    }
+
+    #[test]
+    fn test_sub_bps_fee_charged() {
+        let token_a = AssetId::new([1; 32]);
+        let token_b = AssetId::new([2; 32]);
+        let pool = |fee| {
+            Oxiswap::new(
+                ContractId::zeroed(),
+                token_a,
+                token_b,
+                1_000_000_000,
+                1_000_000_000,
+                fee,
+            )
+        };
+        let amount_in = U256::from(1_000_000);
+
+        // 30.5 bps charges half a bp more than 30 bps.
+        assert!(
+            pool(305)
+                .simulate_swap(token_a, token_b, amount_in)
+                .unwrap()
+                < pool(300)
+                    .simulate_swap(token_a, token_b, amount_in)
+                    .unwrap()
+        );
+    }
+
+    #[test]
+    fn test_dynamic_fee_model() {
+        let token_a = AssetId::new([1; 32]);
+        let token_b = AssetId::new([2; 32]);
+        let pool = Oxiswap::new(
+            ContractId::zeroed(),
+            token_a,
+            token_b,
+            1_000_000_000,
+            1_000_000_000,
+            300,
+        );
+        let dynamic = DynamicFeePool::new(pool, Some(Box::new(SizeFeeModel)));
+        let static_fee = DynamicFeePool::new(pool, None);
+
+        let small = U256::from(1_000_000);
+        assert_eq!(
+            dynamic.simulate_swap(token_a, small).unwrap(),
+            pool.simulate_swap(token_a, token_b, small).unwrap()
+        );
+
+        let large = U256::from(100_000_000);
+        assert_eq!(
+            static_fee.simulate_swap(token_a, large).unwrap(),
+            pool.simulate_swap(token_a, token_b, large).unwrap()
+        );
+        assert!(
+            dynamic.simulate_swap(token_a, large).unwrap()
+                < pool.simulate_swap(token_a, token_b, large).unwrap()
+        );
+    }
+}
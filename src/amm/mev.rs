@@ -0,0 +1,130 @@
+use fuels::types::{AssetId, U256};
+
+use super::{AutomatedMarketMaker, AMM};
+use crate::errors::SwapSimulationError;
+
+/// Estimates the best sandwich of a pending swap of `victim_amount_in` of `victim_token_in`.
+///
+/// Simulates front-run, victim swap and back-run on a copy of `pool` for front-run inputs of up
+/// to `my_max_in`, and returns the most profitable input with its profit in `victim_token_in`.
+/// Rounding makes the profit only roughly unimodal, so the input found is close to, but not
+/// always exactly, the optimum. Returns `None` when no front-run is profitable, and
+/// [`SwapSimulationError::Overflow`] when `victim_amount_in` does not fit in a u64 reserve.
+pub fn sandwich_profit(
+    pool: &AMM,
+    victim_token_in: AssetId,
+    victim_amount_in: U256,
+    my_max_in: U256,
+) -> Result<Option<(U256, U256)>, SwapSimulationError> {
+    // Reserves are u64, so keep every simulated reserve in range
+    let (reserve_0, reserve_1) = pool.reserves();
+    let reserve_in = if pool.tokens().first() == Some(&victim_token_in) {
+        reserve_0
+    } else {
+        reserve_1
+    };
+    let victim_in = u64::try_from(victim_amount_in).map_err(|_| SwapSimulationError::Overflow)?;
+    let headroom = u64::MAX
+        .saturating_sub(reserve_in)
+        .saturating_sub(victim_in);
+    let max_in = u64::try_from(my_max_in).unwrap_or(u64::MAX).min(headroom);
+
+    let profit = |front_run_in: u64| -> i128 {
+        sandwich(pool, victim_token_in, victim_amount_in, front_run_in)
+            .map_or(i128::MIN, |back_run_out| {
+                back_run_out as i128 - front_run_in as i128
+            })
+    };
+
+    let (mut low, mut high) = (0, max_in);
+    while high - low > 2 {
+        let third = (high - low) / 3;
+        if profit(low + third) < profit(high - third) {
+            low += third;
+        } else {
+            high -= third;
+        }
+    }
+
+    let best = (low..=high)
+        .max_by_key(|&front_run_in| profit(front_run_in))
+        .unwrap_or(low);
+    let best_profit = profit(best);
+    if best_profit <= 0 {
+        return Ok(None);
+    }
+    Ok(Some((U256::from(best), U256::from(best_profit as u128))))
+}
+
+/// Simulates the sandwich on a copy of `pool`, returning the back-run output.
+fn sandwich(
+    pool: &AMM,
+    victim_token_in: AssetId,
+    victim_amount_in: U256,
+    front_run_in: u64,
+) -> Option<u128> {
+    let token_out = pool.get_token_out(victim_token_in);
+    let mut pool = pool.clone();
+
+    let front_run_out = pool
+        .simulate_swap_mut(victim_token_in, token_out, U256::from(front_run_in))
+        .ok()?;
+    pool.simulate_swap_mut(victim_token_in, token_out, victim_amount_in)
+        .ok()?;
+    let back_run_out = pool
+        .simulate_swap_mut(token_out, victim_token_in, front_run_out)
+        .ok()?;
+
+    u128::try_from(back_run_out).ok()
+}
+
+#[cfg(test)]
+mod tests {
+    use fuels::types::{AssetId, ContractId, U256};
+
+    use super::sandwich_profit;
+    use crate::{
+        amm::{oxiswap::Oxiswap, AutomatedMarketMaker, AMM},
+        errors::SwapSimulationError,
+    };
+
+    #[test]
+    fn test_sandwich_profit() {
+        let token_a = AssetId::new([1; 32]);
+        let token_b = AssetId::new([2; 32]);
+        let pool = AMM::Oxiswap(Oxiswap::new(
+            ContractId::zeroed(),
+            token_a,
+            token_b,
+            1_000_000_000,
+            1_000_000_000,
+            300,
+        ));
+        let my_max_in = U256::from(1_000_000_000);
+
+        let (front_run_in, profit) =
+            sandwich_profit(&pool, token_a, U256::from(100_000_000), my_max_in)
+                .unwrap()
+                .unwrap();
+        assert!(front_run_in <= my_max_in);
+        assert!(profit > U256::zero());
+        assert_eq!(pool.reserves(), (1_000_000_000, 1_000_000_000));
+
+        // Two fees on the front-run outweigh the impact of a tiny victim swap.
+        assert_eq!(
+            sandwich_profit(&pool, token_a, U256::from(1_000), my_max_in).unwrap(),
+            None
+        );
+
+        // A victim swap no reserve can hold is an error, not an unprofitable sandwich.
+        assert!(matches!(
+            sandwich_profit(
+                &pool,
+                token_a,
+                U256::from(u64::MAX) + U256::one(),
+                my_max_in
+            ),
+            Err(SwapSimulationError::Overflow)
+        ));
+    }
+}
@@ -1,17 +1,156 @@
 use async_trait::async_trait;
-use fuels::{accounts::wallet::Wallet, types::ContractId};
+use fuels::{
+    accounts::wallet::Wallet,
+    types::{AssetId, ContractId},
+};
 use serde::{Deserialize, Serialize};
 
 use crate::{
-    amm::{factory::AutomatedMarketMakerFactory, AMM},
+    amm::{
+        consts::{MIRA_FACTORY_DEFAULT_CHUNK_SIZE, MIRA_FACTORY_DEFAULT_MAX_CONCURRENCY},
+        factory::{populate_in_chunks, AutomatedMarketMakerFactory},
+        mira::{pool_id_from_parts, reader::MiraReader, MiraV1},
+        SimConfig, AMM,
+    },
     errors::AMMError,
 };
 
-#[derive(Default, Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct MiraFactory {
     pub contract_id: ContractId,
     pub creation_block: u64,
     pub fee: u32,
+    // Maximum number of calls in flight while populating a batch
+    pub max_concurrency: usize,
+    // Number of pools populated per batch
+    pub chunk_size: usize,
+    #[serde(skip)]
+    pub sim_config: SimConfig,
+}
+
+impl Default for MiraFactory {
+    fn default() -> Self {
+        MiraFactoryBuilder::new(ContractId::zeroed(), 0).build()
+    }
+}
+
+impl MiraFactory {
+    /// Creates a new Mira factory with the default population tuning.
+    pub fn new(contract_id: ContractId, creation_block: u64) -> Self {
+        MiraFactoryBuilder::new(contract_id, creation_block).build()
+    }
+
+    /// Returns a builder to configure the population tuning of a Mira factory.
+    pub fn builder(contract_id: ContractId, creation_block: u64) -> MiraFactoryBuilder {
+        MiraFactoryBuilder::new(contract_id, creation_block)
+    }
+
+    /// Fetches and populates the volatile and stable pools of a single token pair.
+    ///
+    /// Returns only the pools that exist, so at most two.
+    pub async fn pools_for_pair(
+        &self,
+        token_0: AssetId,
+        token_1: AssetId,
+        wallet: Wallet,
+    ) -> Result<Vec<AMM>, AMMError> {
+        self.pools_for_pair_from(token_0, token_1, &wallet).await
+    }
+
+    /// Reads the volatile and stable pools of a single token pair through `reader`, see
+    /// [`MiraFactory::pools_for_pair`].
+    #[cfg_attr(
+        feature = "tracing",
+        tracing::instrument(
+            name = "pools_for_pair",
+            level = "debug",
+            skip_all,
+            fields(contract_id = %self.contract_id, token_0 = %token_0, token_1 = %token_1)
+        )
+    )]
+    pub async fn pools_for_pair_from<R: MiraReader + ?Sized>(
+        &self,
+        token_0: AssetId,
+        token_1: AssetId,
+        reader: &R,
+    ) -> Result<Vec<AMM>, AMMError> {
+        #[cfg(feature = "tracing")]
+        let _elapsed = crate::telemetry::Elapsed::start();
+
+        let mut amms = vec![];
+        for is_stable in [false, true] {
+            let pool = MiraV1 {
+                address: self.contract_id,
+                pool_id: pool_id_from_parts(token_0, token_1, is_stable),
+                sim_config: self.sim_config,
+                ..Default::default()
+            };
+            if let Some(pool) = pool
+                .try_get_pool_info_from(reader, &self.sim_config)
+                .await?
+            {
+                amms.push(AMM::MiraV1(pool));
+            }
+        }
+
+        Ok(amms)
+    }
+}
+
+/// Builds a [`MiraFactory`] with explicit, per-network population tuning.
+#[derive(Debug, Clone)]
+pub struct MiraFactoryBuilder {
+    contract_id: ContractId,
+    creation_block: u64,
+    fee: u32,
+    max_concurrency: usize,
+    chunk_size: usize,
+    sim_config: SimConfig,
+}
+
+impl MiraFactoryBuilder {
+    /// Creates a new builder with the default population tuning.
+    pub fn new(contract_id: ContractId, creation_block: u64) -> Self {
+        Self {
+            contract_id,
+            creation_block,
+            fee: 0,
+            max_concurrency: MIRA_FACTORY_DEFAULT_MAX_CONCURRENCY,
+            chunk_size: MIRA_FACTORY_DEFAULT_CHUNK_SIZE,
+            sim_config: SimConfig::default(),
+        }
+    }
+
+    pub fn fee(mut self, fee: u32) -> Self {
+        self.fee = fee;
+        self
+    }
+
+    pub fn max_concurrency(mut self, max_concurrency: usize) -> Self {
+        self.max_concurrency = max_concurrency;
+        self
+    }
+
+    pub fn chunk_size(mut self, chunk_size: usize) -> Self {
+        self.chunk_size = chunk_size;
+        self
+    }
+
+    pub fn sim_config(mut self, sim_config: SimConfig) -> Self {
+        self.sim_config = sim_config;
+        self
+    }
+
+    pub fn build(self) -> MiraFactory {
+        MiraFactory {
+            contract_id: self.contract_id,
+            creation_block: self.creation_block,
+            fee: self.fee,
+            max_concurrency: self.max_concurrency,
+            chunk_size: self.chunk_size,
+            sim_config: self.sim_config,
+        }
+    }
 }
 
 #[async_trait]
@@ -40,6 +179,95 @@ impl AutomatedMarketMakerFactory for MiraFactory {
         block_number: Option<u64>,
         wallet: Wallet,
     ) -> Result<(), AMMError> {
-        todo!()
+        populate_in_chunks(
+            amms,
+            block_number,
+            wallet,
+            self.chunk_size,
+            self.max_concurrency,
+        )
+        .await
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use fuels::types::{AssetId, ContractId};
+
+    use super::MiraFactory;
+    use crate::amm::{
+        consts::{MIRA_FACTORY_DEFAULT_CHUNK_SIZE, MIRA_FACTORY_DEFAULT_MAX_CONCURRENCY},
+        mira::reader::{mock::MockReader, PoolReading},
+        AutomatedMarketMaker, AMM,
+    };
+
+    #[test]
+    fn test_builder_custom_tuning() {
+        let contract_id = ContractId::new([1; 32]);
+        let factory = MiraFactory::builder(contract_id, 42)
+            .max_concurrency(2)
+            .chunk_size(25)
+            .build();
+
+        assert_eq!(factory.contract_id, contract_id);
+        assert_eq!(factory.creation_block, 42);
+        assert_eq!(factory.max_concurrency, 2);
+        assert_eq!(factory.chunk_size, 25);
+        let default = MiraFactory::new(contract_id, 42);
+        assert_eq!(
+            default.max_concurrency,
+            MIRA_FACTORY_DEFAULT_MAX_CONCURRENCY
+        );
+        assert_eq!(default.chunk_size, MIRA_FACTORY_DEFAULT_CHUNK_SIZE);
+    }
+
+    #[test]
+    fn test_pools_for_pair() {
+        let low = AssetId::new([1; 32]);
+        let high = AssetId::new([2; 32]);
+        let reading = PoolReading {
+            reserve_0: 1_000,
+            reserve_1: 2_000,
+            decimals_0: 9,
+            decimals_1: 9,
+        };
+        let factory = MiraFactory::new(ContractId::new([5; 32]), 0);
+        let runtime = tokio::runtime::Builder::new_current_thread()
+            .build()
+            .unwrap();
+
+        // Only the volatile pool exists, and the tokens are given out of order.
+        let reader = MockReader {
+            pools: vec![((low, high, false), reading)],
+            fees: (30, 5, 0, 0),
+            ..Default::default()
+        };
+        let amms = runtime
+            .block_on(factory.pools_for_pair_from(high, low, &reader))
+            .unwrap();
+        assert_eq!(amms.len(), 1);
+        let AMM::MiraV1(pool) = &amms[0] else {
+            panic!("expected a Mira pool");
+        };
+        assert_eq!(pool.pool_id, (low, high, false));
+        assert_eq!(pool.address, factory.contract_id);
+        assert_eq!(pool.reserves(), (1_000, 2_000));
+
+        let reader = MockReader {
+            pools: vec![((low, high, false), reading), ((low, high, true), reading)],
+            fees: (30, 5, 0, 0),
+            ..Default::default()
+        };
+        let amms = runtime
+            .block_on(factory.pools_for_pair_from(low, high, &reader))
+            .unwrap();
+        let stable: Vec<bool> = amms.iter().map(|amm| amm.raw_state().is_stable).collect();
+        assert_eq!(stable, vec![false, true]);
+
+        let reader = MockReader::default();
+        assert!(runtime
+            .block_on(factory.pools_for_pair_from(low, high, &reader))
+            .unwrap()
+            .is_empty());
     }
 }
@@ -0,0 +1,106 @@
+use fuels::types::{AssetId, ContractId};
+use serde::{Deserialize, Serialize};
+
+use super::{pool_id_from_parts, MiraV1};
+
+/// A Mira pool as returned by the indexer, whose field names differ from [`MiraV1`].
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct MiraV1Indexed {
+    #[serde(rename = "contractId")]
+    pub contract_id: ContractId,
+    #[serde(rename = "asset0")]
+    pub asset_0: AssetId,
+    #[serde(rename = "asset1")]
+    pub asset_1: AssetId,
+    #[serde(rename = "decimals0")]
+    pub decimals_0: u8,
+    #[serde(rename = "decimals1")]
+    pub decimals_1: u8,
+    #[serde(rename = "reserve0")]
+    pub reserve_0: u64,
+    #[serde(rename = "reserve1")]
+    pub reserve_1: u64,
+    #[serde(rename = "isStable")]
+    pub is_stable: bool,
+    #[serde(rename = "lpFeeVolatile")]
+    pub lp_fee_volatile: u64,
+    #[serde(rename = "lpFeeStable")]
+    pub lp_fee_stable: u64,
+    #[serde(rename = "protocolFeeVolatile")]
+    pub protocol_fee_volatile: u64,
+    #[serde(rename = "protocolFeeStable")]
+    pub protocol_fee_stable: u64,
+}
+
+impl From<MiraV1Indexed> for MiraV1 {
+    /// Converts an indexer payload, reordering the assets into Mira's canonical order if needed.
+    fn from(indexed: MiraV1Indexed) -> Self {
+        let pool_id = pool_id_from_parts(indexed.asset_0, indexed.asset_1, indexed.is_stable);
+        let (decimals, reserves) = if pool_id.0 == indexed.asset_0 {
+            (
+                (indexed.decimals_0, indexed.decimals_1),
+                (indexed.reserve_0, indexed.reserve_1),
+            )
+        } else {
+            (
+                (indexed.decimals_1, indexed.decimals_0),
+                (indexed.reserve_1, indexed.reserve_0),
+            )
+        };
+
+        MiraV1::new(
+            indexed.contract_id,
+            pool_id,
+            pool_id.0,
+            decimals.0,
+            pool_id.1,
+            decimals.1,
+            reserves.0,
+            reserves.1,
+            (
+                indexed.lp_fee_volatile,
+                indexed.lp_fee_stable,
+                indexed.protocol_fee_volatile,
+                indexed.protocol_fee_stable,
+            ),
+            indexed.is_stable,
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use fuels::types::{AssetId, ContractId};
+
+    use super::MiraV1Indexed;
+    use crate::amm::mira::MiraV1;
+
+    #[test]
+    fn test_parse_indexer_json() {
+        let json = r#"{
+            "contractId": "0x0909090909090909090909090909090909090909090909090909090909090909",
+            "asset0": "0x0202020202020202020202020202020202020202020202020202020202020202",
+            "asset1": "0x0101010101010101010101010101010101010101010101010101010101010101",
+            "decimals0": 6,
+            "decimals1": 9,
+            "reserve0": 2000000,
+            "reserve1": 1000000000,
+            "isStable": false,
+            "lpFeeVolatile": 300,
+            "lpFeeStable": 50,
+            "protocolFeeVolatile": 0,
+            "protocolFeeStable": 0
+        }"#;
+        let indexed: MiraV1Indexed = serde_json::from_str(json).unwrap();
+        let pool = MiraV1::from(indexed);
+
+        let low = AssetId::new([1; 32]);
+        let high = AssetId::new([2; 32]);
+        assert_eq!(pool.address, ContractId::new([9; 32]));
+        assert_eq!(pool.pool_id, (low, high, false));
+        assert_eq!((pool.token_0, pool.token_1), (low, high));
+        assert_eq!((pool.token_0_decimals, pool.token_1_decimals), (9, 6));
+        assert_eq!((pool.reserve_0, pool.reserve_1), (1_000_000_000, 2_000_000));
+        assert_eq!(pool.fee, (300, 50, 0, 0));
+    }
+}
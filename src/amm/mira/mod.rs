@@ -1,14 +1,23 @@
 pub mod factory;
-use super::{consts::U128_0X10000000000000000, AutomatedMarketMaker};
+pub mod indexed;
+pub mod reader;
+pub mod sim;
+use std::str::FromStr;
+
+use super::{
+    consts::U128_0X10000000000000000, fee::Bps, q64::Q64, reduce_ratio, AutomatedMarketMaker,
+    RawState, SimConfig,
+};
 use crate::errors::{AMMError, ArithmeticError, SwapSimulationError};
 use async_trait::async_trait;
 use fuels::{
-    accounts::{impersonated_account::ImpersonatedAccount, wallet::Wallet},
-    programs::calls::Execution,
-    types::{transaction::TxPolicies, AssetId, ContractId, U256},
+    accounts::wallet::Wallet,
+    crypto::Hasher,
+    types::{AssetId, ContractId, U256},
 };
-use mira_v1::interface::{PoolId, PoolMetadata};
+use mira_v1::interface::PoolId;
 use num_bigfloat::BigFloat;
+use reader::MiraReader;
 use serde::{Deserialize, Serialize};
 
 /// Represents a Mira pool.
@@ -22,9 +31,27 @@ pub struct MiraV1 {
     pub token_1_decimals: u8,
     pub reserve_0: u64,
     pub reserve_1: u64,
-    // The different fees (lp_fee_volatile, lp_fee_stable, protocol_fee_volatile, protocol_fee_stable)
+    // The different fees (lp_fee_volatile, lp_fee_stable, protocol_fee_volatile, protocol_fee_stable),
+    // raw in tenths of a basis point, see `fee_bps`
     pub fee: (u64, u64, u64, u64),
     pub is_stable: bool,
+    // Cached 10^token_0_decimals and 10^token_1_decimals, see `update_scales`
+    #[serde(default)]
+    pub scale_0: U256,
+    #[serde(default)]
+    pub scale_1: U256,
+    // Swaps revert on chain while the pool is paused. Maintained by the caller, as the AMM
+    // bindings expose no pause getter, and kept across `sync` and `populate_data`
+    #[serde(default)]
+    pub is_paused: bool,
+    // False until the token decimals have been populated, as zero is a valid decimals value.
+    // Defaults to false, also for pools serialized without it, which must be repopulated before
+    // stable swaps or prices
+    #[serde(default)]
+    pub decimals_known: bool,
+    // Used by `sync` and `populate_data`, which take no config of their own
+    #[serde(skip)]
+    pub sim_config: SimConfig,
 }
 
 #[async_trait]
@@ -35,10 +62,7 @@ impl AutomatedMarketMaker for MiraV1 {
 
     /// Synchronizes the AMM's state with the blockchain.
     async fn sync(&mut self, wallet: Wallet) -> Result<(), AMMError> {
-        let (reserve_0, reserve_1) = self.get_reserves(wallet).await?;
-        self.reserve_0 = reserve_0;
-        self.reserve_1 = reserve_1;
-        Ok(())
+        self.sync_from(&wallet).await
     }
 
     fn tokens(&self) -> Vec<AssetId> {
@@ -52,25 +76,20 @@ impl AutomatedMarketMaker for MiraV1 {
         base_token: AssetId,
         _quote_token: AssetId,
     ) -> Result<f64, ArithmeticError> {
+        // The stable price divides by terms of both reserves, the volatile one by the base reserve
+        let reserve_in = if self.token_0 == base_token {
+            self.reserve_0
+        } else {
+            self.reserve_1
+        };
+        if reserve_in == 0 || (self.is_stable && !self.has_liquidity()) {
+            return Err(ArithmeticError::DivisionByZero);
+        }
+
         if self.is_stable {
-            let price: U256 = if self.token_0 == base_token {
-                self.get_stable_price(
-                    U256::from(self.reserve_0),
-                    U256::from(self.reserve_1),
-                    U256::from(self.token_0_decimals),
-                    U256::from(self.token_1_decimals),
-                )
-            } else {
-                self.get_stable_price(
-                    U256::from(self.reserve_1),
-                    U256::from(self.reserve_0),
-                    U256::from(self.token_1_decimals),
-                    U256::from(self.token_0_decimals),
-                )
-            };
-            Ok(u256_to_f64(price))
+            Ok(u256_to_f64(self.stable_price(base_token)?))
         } else {
-            Ok(q64_to_f64(self.calculate_price_64_x_64(base_token)?))
+            Ok(self.calculate_price_64_x_64(base_token)?.to_f64())
         }
     }
 
@@ -80,8 +99,7 @@ impl AutomatedMarketMaker for MiraV1 {
         _block_number: Option<u64>,
         wallet: Wallet,
     ) -> Result<(), AMMError> {
-        *self = self.get_pool_info(wallet).await?;
-        Ok(())
+        self.populate_from(&wallet).await
     }
 
     /// Simulates a swap without modifying the AMM's state.
@@ -91,22 +109,29 @@ impl AutomatedMarketMaker for MiraV1 {
         _quote_token: AssetId,
         amount_in: U256,
     ) -> Result<U256, SwapSimulationError> {
+        if self.is_paused {
+            return Err(SwapSimulationError::PoolPaused);
+        }
+        if self.is_stable && !self.decimals_known {
+            return Err(SwapSimulationError::DecimalsUnknown);
+        }
+
         if self.token_0 == base_token {
-            Ok(self.get_amount_out(
+            self.checked_amount_out_scaled(
                 amount_in,
                 U256::from(self.reserve_0),
                 U256::from(self.reserve_1),
-                U256::from(self.token_0_decimals),
-                U256::from(self.token_1_decimals),
-            ))
+                self.token_scale(0),
+                self.token_scale(1),
+            )
         } else {
-            Ok(self.get_amount_out(
+            self.checked_amount_out_scaled(
                 amount_in,
                 U256::from(self.reserve_1),
                 U256::from(self.reserve_0),
-                U256::from(self.token_1_decimals),
-                U256::from(self.token_0_decimals),
-            ))
+                self.token_scale(1),
+                self.token_scale(0),
+            )
         }
     }
 
@@ -117,33 +142,52 @@ impl AutomatedMarketMaker for MiraV1 {
         _quote_token: AssetId,
         amount_in: U256,
     ) -> Result<U256, SwapSimulationError> {
-        if self.token_0 == base_token {
-            let amount_out = self.get_amount_out(
+        if self.is_paused {
+            return Err(SwapSimulationError::PoolPaused);
+        }
+        if self.is_stable && !self.decimals_known {
+            return Err(SwapSimulationError::DecimalsUnknown);
+        }
+
+        #[cfg(debug_assertions)]
+        let k_before = self.current_k();
+
+        let amount_out = if self.token_0 == base_token {
+            let amount_out = self.checked_amount_out_scaled(
                 amount_in,
                 U256::from(self.reserve_0),
                 U256::from(self.reserve_1),
-                U256::from(self.token_0_decimals),
-                U256::from(self.token_1_decimals),
-            );
+                self.token_scale(0),
+                self.token_scale(1),
+            )?;
 
             self.reserve_0 += amount_in.as_u64();
             self.reserve_1 -= amount_out.as_u64();
 
-            Ok(amount_out)
+            amount_out
         } else {
-            let amount_out = self.get_amount_out(
+            let amount_out = self.checked_amount_out_scaled(
                 amount_in,
                 U256::from(self.reserve_1),
                 U256::from(self.reserve_0),
-                U256::from(self.token_1_decimals),
-                U256::from(self.token_0_decimals),
-            );
+                self.token_scale(1),
+                self.token_scale(0),
+            )?;
 
             self.reserve_0 -= amount_out.as_u64();
             self.reserve_1 += amount_in.as_u64();
 
-            Ok(amount_out)
-        }
+            amount_out
+        };
+
+        // Fees stay in the pool, so a valid swap can never decrease the invariant.
+        #[cfg(debug_assertions)]
+        debug_assert!(
+            self.current_k() >= k_before,
+            "simulate_swap_mut decreased the pool invariant"
+        );
+
+        Ok(amount_out)
     }
 
     fn get_token_out(&self, token_in: AssetId) -> AssetId {
@@ -153,6 +197,41 @@ impl AutomatedMarketMaker for MiraV1 {
             self.token_0
         }
     }
+
+    fn supports_swap(&self, token_in: AssetId, token_out: AssetId) -> bool {
+        (token_in == self.token_0 && token_out == self.token_1)
+            || (token_in == self.token_1 && token_out == self.token_0)
+    }
+
+    fn reserves(&self) -> (u64, u64) {
+        (self.reserve_0, self.reserve_1)
+    }
+
+    fn normalized_reserves(&self) -> (U256, U256) {
+        (
+            self.adjust(U256::from(self.reserve_0), self.token_scale(0)),
+            self.adjust(U256::from(self.reserve_1), self.token_scale(1)),
+        )
+    }
+
+    fn raw_state(&self) -> RawState {
+        RawState {
+            reserve_0: U256::from(self.reserve_0),
+            reserve_1: U256::from(self.reserve_1),
+            decimals_0: self.token_0_decimals,
+            decimals_1: self.token_1_decimals,
+            fee_bps: self.fee_bps().as_bps(),
+            is_stable: self.is_stable,
+        }
+    }
+
+    fn has_liquidity(&self) -> bool {
+        self.reserve_0 != 0 && self.reserve_1 != 0
+    }
+
+    fn lp_asset_id(&self) -> Option<AssetId> {
+        Some(lp_asset_id(self.address, &self.pool_id))
+    }
 }
 
 impl MiraV1 {
@@ -170,7 +249,7 @@ impl MiraV1 {
         fee: (u64, u64, u64, u64),
         is_stable: bool,
     ) -> Self {
-        Self {
+        let mut pool = Self {
             address,
             pool_id,
             token_0,
@@ -181,35 +260,151 @@ impl MiraV1 {
             is_stable,
             token_0_decimals,
             token_1_decimals,
+            scale_0: U256::zero(),
+            scale_1: U256::zero(),
+            is_paused: false,
+            decimals_known: true,
+            sim_config: SimConfig::default(),
+        };
+        pool.update_scales();
+        pool
+    }
+
+    /// Returns the swap fee in basis points, summing the LP and protocol fees of the pool's curve.
+    pub fn fee_bps(&self) -> Bps {
+        if self.is_stable {
+            Bps::from_raw_mira(self.fee.1 + self.fee.3)
+        } else {
+            Bps::from_raw_mira(self.fee.0 + self.fee.2)
+        }
+    }
+
+    /// Checks that the fee applied to swaps in this pool does not exceed `max_fee`.
+    pub fn validate_fee(&self, max_fee: Bps) -> Result<(), AMMError> {
+        if self.fee_bps() > max_fee {
+            return Err(AMMError::FeeTooHigh);
+        }
+
+        Ok(())
+    }
+
+    /// Returns the reserves, to be handed back to [`MiraV1::restore_reserves`].
+    pub fn reserve_checkpoint(&self) -> (u64, u64) {
+        (self.reserve_0, self.reserve_1)
+    }
+
+    /// Rolls the reserves back to a checkpoint from [`MiraV1::reserve_checkpoint`].
+    pub fn restore_reserves(&mut self, cp: (u64, u64)) {
+        (self.reserve_0, self.reserve_1) = cp;
+    }
+
+    /// Recomputes the cached decimal scales from `token_0_decimals` and `token_1_decimals`.
+    ///
+    /// Must be called whenever the token decimals are changed directly.
+    pub fn update_scales(&mut self) {
+        self.scale_0 = pow10(U256::from(self.token_0_decimals));
+        self.scale_1 = pow10(U256::from(self.token_1_decimals));
+    }
+
+    /// Returns 10^decimals of token `index`, 0 or 1, from the cache kept by `update_scales`.
+    fn token_scale(&self, index: usize) -> U256 {
+        let (decimals, scale) = if index == 0 {
+            (self.token_0_decimals, self.scale_0)
+        } else {
+            (self.token_1_decimals, self.scale_1)
+        };
+
+        // Pools built from a struct literal may not have filled the cache
+        if scale.is_zero() {
+            return pow10(U256::from(decimals));
+        }
+        debug_assert_eq!(
+            scale,
+            pow10(U256::from(decimals)),
+            "stale scale_{index}, update_scales must follow a change of decimals"
+        );
+        scale
+    }
+
+    /// Re-derives `token_0` and `token_1` from the `pool_id` when they were left zeroed.
+    pub fn repair_tokens(&mut self) {
+        if self.token_0 == AssetId::zeroed() {
+            self.token_0 = self.pool_id.0;
         }
+        if self.token_1 == AssetId::zeroed() {
+            self.token_1 = self.pool_id.1;
+        }
+    }
+
+    /// Returns `reserve_0 : reserve_1` reduced to lowest terms.
+    pub fn reserve_ratio_reduced(&self) -> (u64, u64) {
+        reduce_ratio(self.reserve_0, self.reserve_1)
     }
 
     /// Fetches the current pool information from the blockchain.
-    pub async fn get_pool_info(&self, wallet: Wallet) -> Result<MiraV1, AMMError> {
-        let address = wallet.address();
-        let provider = wallet.provider();
-        let simulation_account: ImpersonatedAccount =
-            ImpersonatedAccount::new(address.clone(), provider.cloned());
-        let mira_contract =
-            mira_v1::interface::MiraAmmContract::new(self.address, simulation_account);
-
-        let pool_metadata: PoolMetadata = mira_contract
-            .methods()
-            .pool_metadata(self.pool_id)
-            .with_tx_policies(TxPolicies::default())
-            .simulate(Execution::StateReadOnly)
+    pub async fn get_pool_info(
+        &self,
+        wallet: Wallet,
+        sim_config: &SimConfig,
+    ) -> Result<MiraV1, AMMError> {
+        self.get_pool_info_from(&wallet, sim_config).await
+    }
+
+    /// Reads the current pool information through `reader`.
+    pub async fn get_pool_info_from<R: MiraReader + ?Sized>(
+        &self,
+        reader: &R,
+        sim_config: &SimConfig,
+    ) -> Result<MiraV1, AMMError> {
+        self.try_get_pool_info_from(reader, sim_config)
             .await?
-            .value
-            .unwrap();
-        let (lp_fee_volatile, lp_fee_stable, protocol_fee_volatile, protocol_fee_stable) =
-            mira_contract
-                .methods()
-                .fees()
-                .with_tx_policies(TxPolicies::default())
-                .simulate(Execution::StateReadOnly)
-                .await?
-                .value;
-        let mira_pool = MiraV1 {
+            .ok_or(AMMError::PoolNotFound)
+    }
+
+    /// Fetches the current pool information from the blockchain.
+    ///
+    /// Returns `None` if the pool does not exist, and `InvalidTokenPair` without any call if both
+    /// tokens of the pool id are the same.
+    pub async fn try_get_pool_info(
+        &self,
+        wallet: Wallet,
+        sim_config: &SimConfig,
+    ) -> Result<Option<MiraV1>, AMMError> {
+        self.try_get_pool_info_from(&wallet, sim_config).await
+    }
+
+    /// Reads the current pool information through `reader`, see [`MiraV1::try_get_pool_info`].
+    ///
+    /// The returned pool keeps the `sim_config` and `is_paused` flag of this one.
+    #[cfg_attr(
+        feature = "tracing",
+        tracing::instrument(
+            name = "try_get_pool_info",
+            level = "debug",
+            skip_all,
+            fields(contract_id = %self.address, pool_id = ?self.pool_id)
+        )
+    )]
+    pub async fn try_get_pool_info_from<R: MiraReader + ?Sized>(
+        &self,
+        reader: &R,
+        sim_config: &SimConfig,
+    ) -> Result<Option<MiraV1>, AMMError> {
+        #[cfg(feature = "tracing")]
+        let _elapsed = crate::telemetry::Elapsed::start();
+
+        if self.pool_id.0 == self.pool_id.1 {
+            return Err(ArithmeticError::InvalidTokenPair.into());
+        }
+
+        let Some(pool_metadata) = reader
+            .pool_metadata(self.address, self.pool_id, sim_config)
+            .await?
+        else {
+            return Ok(None);
+        };
+        let fee = reader.fees(self.address, sim_config).await?;
+        let mut mira_pool = MiraV1 {
             address: self.address,
             pool_id: self.pool_id,
             token_0: self.pool_id.0,
@@ -218,37 +413,88 @@ impl MiraV1 {
             token_1_decimals: pool_metadata.decimals_1,
             reserve_0: pool_metadata.reserve_0,
             reserve_1: pool_metadata.reserve_1,
-            fee: (
-                lp_fee_volatile,
-                lp_fee_stable,
-                protocol_fee_volatile,
-                protocol_fee_stable,
-            ),
+            fee,
             is_stable: self.pool_id.2,
+            scale_0: U256::zero(),
+            scale_1: U256::zero(),
+            // The AMM contract bindings expose no pause getter, so keep the caller's flag
+            is_paused: self.is_paused,
+            decimals_known: true,
+            sim_config: self.sim_config,
         };
-        Ok(mira_pool)
+        mira_pool.update_scales();
+        mira_pool.validate_fee(sim_config.max_fee)?;
+        Ok(Some(mira_pool))
+    }
+
+    /// Fetches the pool contract's on-chain balances of `token_0` and `token_1`.
+    ///
+    /// These can differ from the reserves, e.g. by accrued protocol fees.
+    pub async fn get_onchain_balances(&self, wallet: Wallet) -> Result<(u64, u64), AMMError> {
+        self.get_onchain_balances_from(&wallet).await
+    }
+
+    /// Reads the pool contract's balances of `token_0` and `token_1` through `reader`.
+    pub async fn get_onchain_balances_from<R: MiraReader + ?Sized>(
+        &self,
+        reader: &R,
+    ) -> Result<(u64, u64), AMMError> {
+        let balance_0 = reader.contract_balance(self.address, self.token_0).await?;
+        let balance_1 = reader.contract_balance(self.address, self.token_1).await?;
+
+        Ok((balance_0, balance_1))
     }
 
     /// Fetches the current reserves from the blockchain.
-    pub async fn get_reserves(&self, wallet: Wallet) -> Result<(u64, u64), AMMError> {
-        let address = wallet.address();
-        let provider = wallet.provider();
-        let simulation_account: ImpersonatedAccount =
-            ImpersonatedAccount::new(address.clone(), provider.cloned());
-        let mira_contract =
-            mira_v1::interface::MiraAmmContract::new(self.address, simulation_account);
-        let pool_metadata: PoolMetadata = mira_contract
-            .methods()
-            .pool_metadata(self.pool_id)
-            .with_tx_policies(TxPolicies::default())
-            .simulate(Execution::StateReadOnly)
+    pub async fn get_reserves(
+        &self,
+        wallet: Wallet,
+        sim_config: &SimConfig,
+    ) -> Result<(u64, u64), AMMError> {
+        self.get_reserves_from(&wallet, sim_config).await
+    }
+
+    /// Reads the current reserves through `reader`.
+    #[cfg_attr(
+        feature = "tracing",
+        tracing::instrument(
+            name = "get_reserves",
+            level = "debug",
+            skip_all,
+            fields(contract_id = %self.address, pool_id = ?self.pool_id)
+        )
+    )]
+    pub async fn get_reserves_from<R: MiraReader + ?Sized>(
+        &self,
+        reader: &R,
+        sim_config: &SimConfig,
+    ) -> Result<(u64, u64), AMMError> {
+        #[cfg(feature = "tracing")]
+        let _elapsed = crate::telemetry::Elapsed::start();
+
+        let pool_metadata = reader
+            .pool_metadata(self.address, self.pool_id, sim_config)
             .await?
-            .value
-            .unwrap();
+            .ok_or(AMMError::PoolNotFound)?;
 
         Ok((pool_metadata.reserve_0, pool_metadata.reserve_1))
     }
 
+    /// Refreshes the reserves through `reader`, using the pool's `sim_config`.
+    pub async fn sync_from<R: MiraReader + ?Sized>(&mut self, reader: &R) -> Result<(), AMMError> {
+        (self.reserve_0, self.reserve_1) = self.get_reserves_from(reader, &self.sim_config).await?;
+        Ok(())
+    }
+
+    /// Reads the whole pool through `reader`, using the pool's `sim_config`.
+    pub async fn populate_from<R: MiraReader + ?Sized>(
+        &mut self,
+        reader: &R,
+    ) -> Result<(), AMMError> {
+        *self = self.get_pool_info_from(reader, &self.sim_config).await?;
+        Ok(())
+    }
+
     /// Calculates the amount received for a given `amount_in` `reserve_in` and `reserve_out`.
 
     pub fn get_amount_out(
@@ -258,6 +504,25 @@ impl MiraV1 {
         reserve_out: U256,
         decimals_in: U256,
         decimals_out: U256,
+    ) -> U256 {
+        self.get_amount_out_scaled(
+            amount_in,
+            reserve_in,
+            reserve_out,
+            pow10(decimals_in),
+            pow10(decimals_out),
+        )
+    }
+
+    /// Calculates the amount out like `get_amount_out`, from the 10^decimals scales of the
+    /// tokens rather than their decimals.
+    fn get_amount_out_scaled(
+        &self,
+        amount_in: U256,
+        reserve_in: U256,
+        reserve_out: U256,
+        scale_in: U256,
+        scale_out: U256,
     ) -> U256 {
         // Early return if any input is zero
         if amount_in.is_zero() || reserve_in.is_zero() || reserve_out.is_zero() {
@@ -265,18 +530,53 @@ impl MiraV1 {
         }
 
         if self.is_stable {
-            self.get_stable_amount_out(
-                amount_in,
-                reserve_in,
-                reserve_out,
-                decimals_in,
-                decimals_out,
-            )
+            self.get_stable_amount_out(amount_in, reserve_in, reserve_out, scale_in, scale_out)
         } else {
             self.get_volatile_amount_out(amount_in, reserve_in, reserve_out)
         }
     }
 
+    /// Calculates the amount out like `get_amount_out`, checking that it leaves part of
+    /// `reserve_out` in the pool.
+    ///
+    /// No valid swap can drain the out reserve, so a larger output means the curve math is wrong
+    /// and is reported as `InsufficientLiquidity`.
+    pub fn checked_amount_out(
+        &self,
+        amount_in: U256,
+        reserve_in: U256,
+        reserve_out: U256,
+        decimals_in: U256,
+        decimals_out: U256,
+    ) -> Result<U256, SwapSimulationError> {
+        self.checked_amount_out_scaled(
+            amount_in,
+            reserve_in,
+            reserve_out,
+            pow10(decimals_in),
+            pow10(decimals_out),
+        )
+    }
+
+    /// Calculates the amount out like `checked_amount_out`, from the 10^decimals scales of the
+    /// tokens rather than their decimals.
+    fn checked_amount_out_scaled(
+        &self,
+        amount_in: U256,
+        reserve_in: U256,
+        reserve_out: U256,
+        scale_in: U256,
+        scale_out: U256,
+    ) -> Result<U256, SwapSimulationError> {
+        let amount_out =
+            self.get_amount_out_scaled(amount_in, reserve_in, reserve_out, scale_in, scale_out);
+        if !amount_out.is_zero() && amount_out >= reserve_out {
+            return Err(SwapSimulationError::InsufficientLiquidity);
+        }
+
+        Ok(amount_out)
+    }
+
     /// Calculates the output amount for a volatile (constant product) pool.
     fn get_volatile_amount_out(
         &self,
@@ -284,8 +584,8 @@ impl MiraV1 {
         reserve_in: U256,
         reserve_out: U256,
     ) -> U256 {
-        let fee_numerator = U256::from(10000 - ((self.fee.0 + self.fee.2) / 10));
-        let fee_denominator = U256::from(10000);
+        let fee_numerator = U256::from(self.fee_bps().complement_raw());
+        let fee_denominator = U256::from(Bps::RAW_DENOMINATOR);
 
         let amount_in_with_fee = amount_in * fee_numerator;
         let numerator = amount_in_with_fee * reserve_out;
@@ -300,93 +600,197 @@ impl MiraV1 {
         amount_in: U256,
         reserve_in: U256,
         reserve_out: U256,
-        decimals_in: U256,
-        decimals_out: U256,
+        scale_in: U256,
+        scale_out: U256,
     ) -> U256 {
-        let xy = self.k(true, reserve_in, reserve_out, decimals_in, decimals_out);
-        let amount_in_adjusted = self.adjust(amount_in, decimals_in);
-        let reserve_in_adjusted = self.adjust(reserve_in, decimals_in);
-        let reserve_out_adjusted = self.adjust(reserve_out, decimals_out);
-
-        let y = reserve_out_adjusted
-            - self.y(
-                amount_in_adjusted + reserve_in_adjusted,
-                xy,
-                reserve_out_adjusted,
-            );
+        // Adjusting 18 decimal amounts is the identity, so skip it for 18/18 pools.
+        let one_e_18 = self.one_e_18();
+        if scale_in == one_e_18 && scale_out == one_e_18 {
+            return self.get_stable_amount_out_adjusted(amount_in, reserve_in, reserve_out);
+        }
+
+        self.get_stable_amount_out_general(amount_in, reserve_in, reserve_out, scale_in, scale_out)
+    }
+
+    /// Calculates the output amount for a stable pool, adjusting every amount to 18 decimals.
+    fn get_stable_amount_out_general(
+        &self,
+        amount_in: U256,
+        reserve_in: U256,
+        reserve_out: U256,
+        scale_in: U256,
+        scale_out: U256,
+    ) -> U256 {
+        let amount_in_adjusted = self.adjust(amount_in, scale_in);
+        let reserve_in_adjusted = self.adjust(reserve_in, scale_in);
+        let reserve_out_adjusted = self.adjust(reserve_out, scale_out);
+
+        let y = self.get_stable_amount_out_adjusted(
+            amount_in_adjusted,
+            reserve_in_adjusted,
+            reserve_out_adjusted,
+        );
+
+        self.unadjust(y, scale_out)
+    }
+
+    /// Calculates the output amount for a stable pool from amounts already in 18 decimals.
+    fn get_stable_amount_out_adjusted(
+        &self,
+        amount_in: U256,
+        reserve_in: U256,
+        reserve_out: U256,
+    ) -> U256 {
+        let xy = self.stable_k(reserve_in, reserve_out);
+        reserve_out - self.y(amount_in + reserve_in, xy, reserve_out)
+    }
+
+    /// Calculates the swap bringing the decimal-adjusted reserves of a stable pool back to 1:1.
+    ///
+    /// Returns the token to sell and the smallest amount of it reaching the peg, found by
+    /// bisection over `simulate_swap`. Volatile pools return `UnsupportedPool`.
+    pub fn amount_to_rebalance(&self) -> Result<(AssetId, U256), SwapSimulationError> {
+        if !self.is_stable {
+            return Err(SwapSimulationError::UnsupportedPool);
+        }
 
-        self.unadjust(y, decimals_out)
+        let scale_0 = self.token_scale(0);
+        let scale_1 = self.token_scale(1);
+        let adjusted_0 = self.adjust(U256::from(self.reserve_0), scale_0);
+        let adjusted_1 = self.adjust(U256::from(self.reserve_1), scale_1);
+
+        // Sell the scarcer token, whose reserve has to grow to meet the other one
+        let (token_in, reserve_in, scale_in, reserve_out, scale_out, gap) =
+            if adjusted_0 < adjusted_1 {
+                (
+                    self.token_0,
+                    U256::from(self.reserve_0),
+                    scale_0,
+                    U256::from(self.reserve_1),
+                    scale_1,
+                    adjusted_1 - adjusted_0,
+                )
+            } else {
+                (
+                    self.token_1,
+                    U256::from(self.reserve_1),
+                    scale_1,
+                    U256::from(self.reserve_0),
+                    scale_0,
+                    adjusted_0 - adjusted_1,
+                )
+            };
+        let token_out = self.get_token_out(token_in);
+
+        // Selling the whole gap already closes it, before counting the output leaving the pool
+        let mut low = U256::zero();
+        let mut high = self.unadjust(gap, scale_in) + 1;
+        while low < high {
+            let mid = (low + high) / 2;
+            let amount_out = self.simulate_swap(token_in, token_out, mid)?;
+            if self.adjust(reserve_in + mid, scale_in)
+                >= self.adjust(reserve_out - amount_out, scale_out)
+            {
+                high = mid;
+            } else {
+                low = mid + U256::one();
+            }
+        }
+
+        Ok((token_in, low))
     }
 
     /// Calculates the invariant k for the pool.
     ///
     /// For stable pools: k = (x^3 * y + y^3 * x) / 10^18
     /// For volatile pools: k = x * y
-    fn k(&self, is_stable: bool, x: U256, y: U256, decimals_x: U256, decimals_y: U256) -> U256 {
+    fn k(&self, is_stable: bool, x: U256, y: U256, scale_x: U256, scale_y: U256) -> U256 {
         if is_stable {
-            let x_adjusted = self.adjust(x, decimals_x);
-            let y_adjusted = self.adjust(y, decimals_y);
-
-            let a = (x_adjusted * y_adjusted) / self.one_e_18();
-            let b = (x_adjusted * x_adjusted) / self.one_e_18()
-                + (y_adjusted * y_adjusted) / self.one_e_18();
-
-            (a * b) / self.one_e_18() // (x^3 * y + y^3 * x) / 10^18
+            self.stable_k(self.adjust(x, scale_x), self.adjust(y, scale_y))
         } else {
             x * y // xy >= k
         }
     }
 
+    /// Calculates the stable invariant from reserves already in 18 decimals.
+    fn stable_k(&self, x: U256, y: U256) -> U256 {
+        let a = (x * y) / self.one_e_18();
+        let b = (x * x) / self.one_e_18() + (y * y) / self.one_e_18();
+
+        (a * b) / self.one_e_18() // (x^3 * y + y^3 * x) / 10^18
+    }
+
+    /// Calculates the invariant k for the current reserves.
+    #[cfg(debug_assertions)]
+    fn current_k(&self) -> U256 {
+        self.k(
+            self.is_stable,
+            U256::from(self.reserve_0),
+            U256::from(self.reserve_1),
+            self.token_scale(0),
+            self.token_scale(1),
+        )
+    }
+
     /// Calculates the y value for the stable swap equation.
+    ///
+    /// Returns the smallest y with `f(x_0, y) >= xy`, rounding in the pool's favour so the
+    /// output rounds down and a swap never decreases the invariant.
     fn y(&self, x_0: U256, xy: U256, y: U256) -> U256 {
         let mut y = y;
         for _ in 0..255 {
-            let y_prev = y;
             let k = self.f(x_0, y);
 
             if k < xy {
-                let dy = ((xy - k) * self.one_e_18()) / self.d(x_0, y);
+                let mut dy = ((xy - k) * self.one_e_18()) / self.d(x_0, y);
+                if dy.is_zero() {
+                    if self.f(x_0, y + 1) > xy {
+                        return y + 1;
+                    }
+                    dy = U256::one();
+                }
                 y += dy;
             } else {
-                let dy = ((k - xy) * self.one_e_18()) / self.d(x_0, y);
-                y = y.saturating_sub(dy);
-            }
-
-            if y > y_prev {
-                if y - y_prev <= U256::from(1) {
-                    return y;
+                let mut dy = ((k - xy) * self.one_e_18()) / self.d(x_0, y);
+                if dy.is_zero() {
+                    if k == xy || y.is_zero() || self.f(x_0, y - 1) < xy {
+                        return y;
+                    }
+                    dy = U256::one();
                 }
-            } else if y_prev - y <= U256::from(1) {
-                return y;
+                y = y.saturating_sub(dy);
             }
         }
         y
     }
 
     /// Calculates f(x,y) = x^3*y + y^3*x for the stable swap equation.
+    ///
+    /// Uses the same rounding as `k` so the solver converges on the pool invariant.
     fn f(&self, x: U256, y: U256) -> U256 {
-        let x_squared = (x * x) / self.one_e_18();
-        let y_squared = (y * y) / self.one_e_18();
+        let a = (x * y) / self.one_e_18();
+        let b = (x * x) / self.one_e_18() + (y * y) / self.one_e_18();
 
-        ((x * y_squared) / self.one_e_18()) + ((y * x_squared) / self.one_e_18())
+        (a * b) / self.one_e_18()
     }
 
-    /// Calculates d(x,y) = 3x^2*y + y^3 for the stable swap equation.
+    /// Calculates d(x,y) = 3x*y^2 + x^3, the derivative of f with respect to y.
     fn d(&self, x: U256, y: U256) -> U256 {
         let x_squared = (x * x) / self.one_e_18();
         let y_squared = (y * y) / self.one_e_18();
 
-        U256::from(3) * ((x_squared * y) / self.one_e_18()) + y_squared
+        U256::from(3) * x * y_squared / self.one_e_18() + (x_squared * x) / self.one_e_18()
     }
 
-    /// Adjusts the amount to 18 decimal places for internal calculations.
-    fn adjust(&self, amount: U256, decimals: U256) -> U256 {
-        amount * self.one_e_18() / U256::from(10).pow(decimals)
+    /// Adjusts the amount from a token with the given 10^decimals `scale` to 18 decimal places
+    /// for internal calculations.
+    fn adjust(&self, amount: U256, scale: U256) -> U256 {
+        amount * self.one_e_18() / scale
     }
 
-    /// Unadjusts the amount from 18 decimal places to the original decimal places.
-    fn unadjust(&self, amount: U256, decimals: U256) -> U256 {
-        amount * U256::from(10).pow(decimals) / self.one_e_18()
+    /// Unadjusts the amount from 18 decimal places to a token with the given 10^decimals `scale`.
+    fn unadjust(&self, amount: U256, scale: U256) -> U256 {
+        amount * scale / self.one_e_18()
     }
 
     /// Returns 10^18 as a U256 value.
@@ -394,117 +798,261 @@ impl MiraV1 {
         U256::from(10).pow(U256::from(18))
     }
     /// Calculates the price of the base token in terms of the quote token.
-    ///
-    /// Returned as a Q64 fixed point number.
-    pub fn calculate_price_64_x_64(&self, base_token: AssetId) -> Result<u128, ArithmeticError> {
-        let decimal_shift = self.token_0_decimals as i8 - self.token_1_decimals as i8;
+    pub fn calculate_price_64_x_64(&self, base_token: AssetId) -> Result<Q64, ArithmeticError> {
+        self.ensure_decimals_known()?;
+        let scale_0 = self.token_scale(0);
+        let scale_1 = self.token_scale(1);
 
-        let (r_a, r_1) = if decimal_shift < 0 {
+        let (r_a, r_1) = if scale_0 < scale_1 {
             (
-                U256::from(self.reserve_0)
-                    * U256::from(10u128.pow(decimal_shift.unsigned_abs() as u32)),
+                U256::from(self.reserve_0) * (scale_1 / scale_0),
                 U256::from(self.reserve_1),
             )
         } else {
             (
                 U256::from(self.reserve_0),
-                U256::from(self.reserve_1) * U256::from(10u128.pow(decimal_shift as u32)),
+                U256::from(self.reserve_1) * (scale_0 / scale_1),
             )
         };
 
-        if base_token == self.token_0 {
+        // An empty base reserve saturates to the largest representable price
+        let price = if base_token == self.token_0 {
             if r_a.is_zero() {
-                Ok(U256::max_value().as_u128())
+                u128::MAX
             } else {
-                div_uu(r_1, r_a)
+                div_uu(r_1, r_a)?
             }
         } else if r_1.is_zero() {
-            Ok(U256::max_value().as_u128())
+            u128::MAX
         } else {
-            div_uu(r_a, r_1)
-        }
+            div_uu(r_a, r_1)?
+        };
+        Ok(Q64(price))
     }
-    pub fn get_stable_price(
-        &self,
-        reserve_x: U256,
-        reserve_y: U256,
-        decimals_x: U256,
-        decimals_y: U256,
-    ) -> U256 {
-        // Adjust reserves to 18 decimal places
-        let x = self.adjust(reserve_x, decimals_x);
-        let y = self.adjust(reserve_y, decimals_y);
 
-        // Calculate x^3 and y^3
-        let x3 = x.pow(U256::from(3)) / self.one_e_18().pow(U256::from(2));
-        let y3 = y.pow(U256::from(3)) / self.one_e_18().pow(U256::from(2));
-
-        // Calculate the price using the derivative of the stable curve formula
-        let numerator = x3 + self.one_e_18() * x * y;
-        let denominator = y3 + self.one_e_18() * x * y;
-
-        // The price is (y^3 + xy) / (x^3 + xy)
-        let price = (numerator * self.one_e_18()) / denominator;
-
-        // Adjust the price for the difference in token decimals
-        if decimals_x >= decimals_y {
-            price * U256::from(10).pow(decimals_x - decimals_y)
+    /// Calculates the price of the base token in terms of the quote token.
+    ///
+    /// Returned as a Q64 fixed point number for both volatile and stable pools, avoiding the
+    /// lossy `f64` conversion of `calculate_price`.
+    pub fn price_q64(&self, base_token: AssetId) -> Result<Q64, ArithmeticError> {
+        if self.is_stable {
+            Ok(Q64(div_uu(
+                self.stable_price(base_token)?,
+                self.one_e_18(),
+            )?))
         } else {
-            price / U256::from(10).pow(decimals_y - decimals_x)
+            self.calculate_price_64_x_64(base_token)
         }
     }
-}
 
-pub fn div_uu(x: U256, y: U256) -> Result<u128, ArithmeticError> {
-    if !y.is_zero() {
-        let mut answer;
-
-        if x <= U256::max_value() {
-            answer = (x << U256::from(64)) / y;
+    /// Returns `DecimalsUnknown` until the token decimals have been populated.
+    fn ensure_decimals_known(&self) -> Result<(), ArithmeticError> {
+        if self.decimals_known {
+            Ok(())
         } else {
-            let mut msb = U256::from(192);
-            let mut xc = x >> U256::from(192);
-
-            // TODO: Figure out how to do the little endian conversion. Might be worth using primitive types crate rather than Fuel U256
-            if xc >= U256::from_dec_str("4294967296").unwrap() {
-                xc >>= U256::from(32);
-                msb += U256::from(32);
-            }
-
-            if xc >= U256::from(65536) {
-                xc >>= U256::from(16);
-                msb += U256::from(16);
-            }
-
-            if xc >= U256::from(256) {
-                xc >>= U256::from(8);
-                msb += U256::from(8);
-            }
-
-            if xc >= U256::from(16) {
-                xc >>= U256::from(4);
-                msb += U256::from(4);
-            }
-
-            if xc >= U256::from(4) {
-                xc >>= U256::from(2);
-                msb += U256::from(2);
-            }
-
-            if xc >= U256::from(2) {
-                msb += U256::one();
-            }
-
-            answer = (x << (U256::from(255) - msb))
-                / (((y - U256::one()) >> (msb - U256::from(191))) + U256::one());
+            Err(ArithmeticError::DecimalsUnknown)
         }
+    }
 
-        if answer > U256::max_value() {
-            return Ok(0);
+    /// Calculates the stable price of the base token, scaled by 10^18.
+    fn stable_price(&self, base_token: AssetId) -> Result<U256, ArithmeticError> {
+        self.ensure_decimals_known()?;
+        if self.token_0 == base_token {
+            self.get_stable_price(
+                U256::from(self.reserve_0),
+                U256::from(self.reserve_1),
+                U256::from(self.token_0_decimals),
+                U256::from(self.token_1_decimals),
+            )
+        } else {
+            self.get_stable_price(
+                U256::from(self.reserve_1),
+                U256::from(self.reserve_0),
+                U256::from(self.token_1_decimals),
+                U256::from(self.token_0_decimals),
+            )
+        }
+    }
+
+    /// Calculates the stable price of the base token as an exact `(numerator, denominator)` ratio.
+    ///
+    /// The decimal adjustment is applied to the ratio itself, so callers needing more precision
+    /// than `calculate_price` can avoid the `U256` -> `f64` conversion.
+    pub fn stable_price_ratio(&self, base_token: AssetId) -> Result<(U256, U256), ArithmeticError> {
+        self.ensure_decimals_known()?;
+        let (numerator, denominator) = if self.token_0 == base_token {
+            self.get_stable_price_ratio(
+                U256::from(self.reserve_0),
+                U256::from(self.reserve_1),
+                U256::from(self.token_0_decimals),
+                U256::from(self.token_1_decimals),
+            )
+        } else {
+            self.get_stable_price_ratio(
+                U256::from(self.reserve_1),
+                U256::from(self.reserve_0),
+                U256::from(self.token_1_decimals),
+                U256::from(self.token_0_decimals),
+            )
+        }?;
+
+        if denominator.is_zero() {
+            return Err(ArithmeticError::DivisionByZero);
+        }
+        Ok((numerator, denominator))
+    }
+
+    /// Calculates the stable price of `x` in `y`, scaled by 10^18.
+    ///
+    /// Returns `Overflow` instead of panicking when deep reserves exceed `U256`.
+    pub fn get_stable_price(
+        &self,
+        reserve_x: U256,
+        reserve_y: U256,
+        decimals_x: U256,
+        decimals_y: U256,
+    ) -> Result<U256, ArithmeticError> {
+        let (numerator, denominator) =
+            self.stable_curve_terms(reserve_x, reserve_y, decimals_x, decimals_y)?;
+        if denominator.is_zero() {
+            return Err(ArithmeticError::DivisionByZero);
+        }
+
+        // The price is (y^3 + xy) / (x^3 + xy)
+        let price = numerator
+            .checked_mul(self.one_e_18())
+            .ok_or(ArithmeticError::Overflow)?
+            / denominator;
+
+        // Adjust the price for the difference in token decimals
+        if decimals_x >= decimals_y {
+            price
+                .checked_mul(pow10(decimals_x) / pow10(decimals_y))
+                .ok_or(ArithmeticError::Overflow)
+        } else {
+            Ok(price / (pow10(decimals_y) / pow10(decimals_x)))
+        }
+    }
+
+    /// Calculates the stable price as a `(numerator, denominator)` ratio, adjusted for the
+    /// difference in token decimals.
+    pub fn get_stable_price_ratio(
+        &self,
+        reserve_x: U256,
+        reserve_y: U256,
+        decimals_x: U256,
+        decimals_y: U256,
+    ) -> Result<(U256, U256), ArithmeticError> {
+        let (numerator, denominator) =
+            self.stable_curve_terms(reserve_x, reserve_y, decimals_x, decimals_y)?;
+
+        if decimals_x >= decimals_y {
+            let numerator = numerator
+                .checked_mul(U256::from(10).pow(decimals_x - decimals_y))
+                .ok_or(ArithmeticError::Overflow)?;
+            Ok((numerator, denominator))
+        } else {
+            let denominator = denominator
+                .checked_mul(U256::from(10).pow(decimals_y - decimals_x))
+                .ok_or(ArithmeticError::Overflow)?;
+            Ok((numerator, denominator))
+        }
+    }
+
+    /// Calculates the numerator and denominator of the stable curve derivative.
+    ///
+    /// Every step is checked, returning `Overflow` for reserves too deep to fit in `U256`.
+    fn stable_curve_terms(
+        &self,
+        reserve_x: U256,
+        reserve_y: U256,
+        decimals_x: U256,
+        decimals_y: U256,
+    ) -> Result<(U256, U256), ArithmeticError> {
+        // Adjust reserves to 18 decimal places
+        let x = self.adjust(reserve_x, pow10(decimals_x));
+        let y = self.adjust(reserve_y, pow10(decimals_y));
+
+        // Calculate x^3 and y^3
+        let one_e_36 = self.one_e_18().pow(U256::from(2));
+        let x3 = x
+            .checked_pow(U256::from(3))
+            .ok_or(ArithmeticError::Overflow)?
+            / one_e_36;
+        let y3 = y
+            .checked_pow(U256::from(3))
+            .ok_or(ArithmeticError::Overflow)?
+            / one_e_36;
+
+        // Calculate the price using the derivative of the stable curve formula
+        let xy = self
+            .one_e_18()
+            .checked_mul(x)
+            .and_then(|value| value.checked_mul(y))
+            .ok_or(ArithmeticError::Overflow)?;
+        let numerator = x3.checked_add(xy).ok_or(ArithmeticError::Overflow)?;
+        let denominator = y3.checked_add(xy).ok_or(ArithmeticError::Overflow)?;
+
+        Ok((numerator, denominator))
+    }
+}
+
+/// Calculates `x / y` as a Q64 fixed point number, ported from ABDK's `divuu`.
+///
+/// `x << 64` only fits in `U256` for `x < 2^192`, so larger values of `x` take the wide path
+/// instead of wrapping. Returns `Overflow` when the result does not fit in a `u128`.
+pub fn div_uu(x: U256, y: U256) -> Result<u128, ArithmeticError> {
+    if y.is_zero() {
+        return Err(ArithmeticError::YIsZero);
+    }
+
+    let u128_max = U256::from(u128::MAX);
+    let mut answer;
+
+    if x < U256::one() << U256::from(192) {
+        answer = (x << U256::from(64)) / y;
+    } else {
+        let mut msb = U256::from(192);
+        let mut xc = x >> U256::from(192);
+
+        if xc >= U256::from(4294967296u64) {
+            xc >>= U256::from(32);
+            msb += U256::from(32);
+        }
+
+        if xc >= U256::from(65536) {
+            xc >>= U256::from(16);
+            msb += U256::from(16);
+        }
+
+        if xc >= U256::from(256) {
+            xc >>= U256::from(8);
+            msb += U256::from(8);
+        }
+
+        if xc >= U256::from(16) {
+            xc >>= U256::from(4);
+            msb += U256::from(4);
+        }
+
+        if xc >= U256::from(4) {
+            xc >>= U256::from(2);
+            msb += U256::from(2);
+        }
+
+        if xc >= U256::from(2) {
+            msb += U256::one();
+        }
+
+        // Dividing by a rounded up `y` underestimates the result, corrected below
+        answer = (x << (U256::from(255) - msb))
+            / (((y - U256::one()) >> (msb - U256::from(191))) + U256::one());
+        if answer > u128_max {
+            return Err(ArithmeticError::Overflow);
         }
 
         let hi = answer * (y >> U256::from(128));
-        let mut lo = answer * (y & U256::max_value());
+        let mut lo = answer * (y & u128_max);
 
         let mut xh = x >> U256::from(192);
         let mut xl = x << U256::from(64);
@@ -527,15 +1075,13 @@ pub fn div_uu(x: U256, y: U256) -> Result<u128, ArithmeticError> {
         }
 
         answer += xl / y;
+    }
 
-        if answer > U256::max_value() {
-            return Ok(0_u128);
-        }
-
-        Ok(answer.as_u128())
-    } else {
-        Err(ArithmeticError::YIsZero)
+    if answer > u128_max {
+        return Err(ArithmeticError::Overflow);
     }
+
+    Ok(answer.as_u128())
 }
 
 /// Converts a Q64 fixed point to a Q16 fixed point -> f64
@@ -544,16 +1090,104 @@ pub fn q64_to_f64(x: u128) -> f64 {
         .div(&BigFloat::from(U128_0X10000000000000000))
         .to_f64()
 }
+
+/// Converts a Q64 fixed point to f64 without going through `BigFloat`.
+///
+/// Shifting by 64 bits is exact in f64, so this only rounds `x` to 53 significant bits.
+pub fn q64_to_f64_fast(x: u128) -> f64 {
+    x as f64 / U128_0X10000000000000000 as f64
+}
 pub fn u256_to_f64(value: U256) -> f64 {
     let one_e18 = U256::from(10).pow(U256::from(18));
     let whole_part = (value / one_e18).as_u128() as f64;
     let fractional_part = (value % one_e18).as_u128() as f64 / 1e18;
     whole_part + fractional_part
 }
+
+/// Returns 10^decimals.
+fn pow10(decimals: U256) -> U256 {
+    U256::from(10).pow(decimals)
+}
+
+/// Builds a Mira `PoolId` from its parts, sorting the tokens into Mira's canonical order.
+pub fn pool_id_from_parts(token_0: AssetId, token_1: AssetId, is_stable: bool) -> PoolId {
+    if token_0 < token_1 {
+        (token_0, token_1, is_stable)
+    } else {
+        (token_1, token_0, is_stable)
+    }
+}
+
+/// Parses a Mira `PoolId` from `token0:token1:stable`, with hex tokens and a `true`/`false`
+/// stable flag. The tokens are sorted into Mira's canonical order.
+pub fn parse_pool_id(s: &str) -> Result<PoolId, AMMError> {
+    let mut parts = s.trim().split(':');
+    let (Some(token_0), Some(token_1), Some(is_stable), None) =
+        (parts.next(), parts.next(), parts.next(), parts.next())
+    else {
+        return Err(AMMError::InvalidPoolId);
+    };
+
+    let token_0 = AssetId::from_str(token_0).map_err(|_| AMMError::InvalidPoolId)?;
+    let token_1 = AssetId::from_str(token_1).map_err(|_| AMMError::InvalidPoolId)?;
+    let is_stable = bool::from_str(is_stable).map_err(|_| AMMError::InvalidPoolId)?;
+    Ok(pool_id_from_parts(token_0, token_1, is_stable))
+}
+
+/// Formats a Mira `PoolId` as `token0:token1:stable`, the inverse of [`parse_pool_id`].
+pub fn pool_id_to_string(id: &PoolId) -> String {
+    format!("{:#x}:{:#x}:{}", id.0, id.1, id.2)
+}
+
+/// Derives the LP asset of a Mira pool, minted by the AMM contract under `sha256(pool_id)`.
+pub fn lp_asset_id(contract_id: ContractId, pool_id: &PoolId) -> AssetId {
+    let sub_id = Hasher::default()
+        .chain(pool_id.0)
+        .chain(pool_id.1)
+        .chain([pool_id.2 as u8])
+        .finalize();
+    let asset_id = Hasher::default()
+        .chain(contract_id)
+        .chain(sub_id)
+        .finalize();
+    AssetId::new(*asset_id)
+}
+
+/// Computes reserves putting a volatile pool at `price`, the price of token 0 in token 1.
+///
+/// `total_value` is expressed in raw units of token 1 and split evenly between both sides.
+/// Reserves that do not fit in a `u64` saturate.
+pub fn reserves_for_price(
+    total_value: U256,
+    price: f64,
+    decimals_0: u8,
+    decimals_1: u8,
+) -> (u64, u64) {
+    let reserve_1 = (total_value / 2).min(U256::from(u64::MAX)).as_u64();
+    let reserve_0 =
+        reserve_1 as f64 * 10f64.powi(decimals_0 as i32) / (10f64.powi(decimals_1 as i32) * price);
+
+    (reserve_0.round() as u64, reserve_1)
+}
+
+/// Splits a Mira `PoolId` into `(token_0, token_1, is_stable)`.
+pub fn parts_from_pool_id(id: &PoolId) -> (AssetId, AssetId, bool) {
+    (id.0, id.1, id.2)
+}
+#[cfg(test)]
 #[allow(unused_imports)]
 mod tests {
-    use crate::amm::{mira::MiraV1, AutomatedMarketMaker};
-    use fuels::types::{AssetId, ContractId};
+    use crate::amm::fee::Bps;
+    use crate::amm::q64::Q64;
+    use crate::amm::{
+        mira::{
+            div_uu, parse_pool_id, parts_from_pool_id, pool_id_from_parts, pool_id_to_string,
+            pow10, reserves_for_price, u256_to_f64, MiraV1,
+        },
+        AutomatedMarketMaker, SimConfig,
+    };
+    use crate::errors::{AMMError, ArithmeticError, SwapSimulationError};
+    use fuels::types::{AssetId, ContractId, U256};
     use mira_v1::interface::PoolId;
 
     #[test]
@@ -571,9 +1205,881 @@ mod tests {
             reserve_1: 15466423,
             fee: (300, 300, 300, 300),
             is_stable: false,
+            scale_0: U256::from(10).pow(U256::from(18)),
+            scale_1: U256::from(10).pow(U256::from(9)),
+            is_paused: false,
+            decimals_known: true,
+            sim_config: SimConfig::default(),
         };
 
         assert!(x.calculate_price(token_0, AssetId::default()).unwrap() != 0.0);
         assert!(x.calculate_price(token_1, AssetId::default()).unwrap() != 0.0);
     }
+
+    #[test]
+    fn test_pool_id_from_parts_canonical_order() {
+        let low = AssetId::new([1; 32]);
+        let high = AssetId::new([2; 32]);
+
+        let pool_id = pool_id_from_parts(high, low, true);
+        assert_eq!(pool_id, pool_id_from_parts(low, high, true));
+        assert_eq!(parts_from_pool_id(&pool_id), (low, high, true));
+    }
+
+    #[test]
+    fn test_parse_pool_id() {
+        let low = AssetId::new([1; 32]);
+        let high = AssetId::new([2; 32]);
+
+        for pool_id in [(low, high, true), (low, high, false)] {
+            let s = pool_id_to_string(&pool_id);
+            assert_eq!(parse_pool_id(&s).unwrap(), pool_id);
+        }
+
+        // Tokens may come without a 0x prefix and in either order.
+        let s = format!("{:x}:{:x}:false", high, low);
+        assert_eq!(parse_pool_id(&s).unwrap(), (low, high, false));
+
+        let low_hex = format!("{:#x}", low);
+        for malformed in [
+            String::new(),
+            format!("{low_hex}:{low_hex}"),
+            format!("{low_hex}:{low_hex}:true:true"),
+            format!("{low_hex}:{low_hex}:yes"),
+            format!("{low_hex}:0x1234:true"),
+            format!("{low_hex}:zz:false"),
+        ] {
+            assert!(matches!(
+                parse_pool_id(&malformed),
+                Err(AMMError::InvalidPoolId)
+            ));
+        }
+    }
+
+    #[test]
+    fn test_price_oriented() {
+        let pool = volatile_pool();
+        let (token_0, token_1) = (pool.token_0, pool.token_1);
+
+        let price_0 = pool.price_oriented(token_0, token_1).unwrap();
+        let price_1 = pool.price_oriented(token_1, token_0).unwrap();
+        assert!((price_0 - 1.2).abs() < 1e-9);
+        assert!((price_1 - 1.0 / 1.2).abs() < 1e-9);
+        assert_eq!(price_0, pool.calculate_price(token_0, token_1).unwrap());
+
+        assert!(matches!(
+            pool.price_oriented(token_0, AssetId::new([3; 32])),
+            Err(ArithmeticError::InvalidTokenPair)
+        ));
+    }
+
+    #[test]
+    fn test_validate_fee() {
+        let max_fee = Bps::from_bps(1000);
+        assert!(volatile_pool().validate_fee(max_fee).is_ok());
+
+        let over_max = MiraV1 {
+            fee: (9_000, 50, 1_010, 0),
+            ..volatile_pool()
+        };
+        assert!(matches!(
+            over_max.validate_fee(max_fee),
+            Err(AMMError::FeeTooHigh)
+        ));
+
+        // Only the fee of the pool's own curve applies.
+        let stable = MiraV1 {
+            is_stable: true,
+            ..over_max
+        };
+        assert!(stable.validate_fee(max_fee).is_ok());
+    }
+
+    #[test]
+    fn test_stable_amount_out_18_decimals_fast_path() {
+        let reserve = U256::from(10).pow(U256::from(24));
+        let mut pool = MiraV1 {
+            token_0_decimals: 18,
+            token_1_decimals: 18,
+            is_stable: true,
+            ..volatile_pool()
+        };
+        pool.update_scales();
+        let scale = pow10(U256::from(18));
+
+        for amount_in in [1u64, 1_000_000_000, 1_000_000_000_000_000_000] {
+            let amount_in = U256::from(amount_in);
+            let reserve_out = reserve + U256::from(12_345);
+            assert_eq!(
+                pool.get_stable_amount_out(amount_in, reserve, reserve_out, scale, scale),
+                pool.get_stable_amount_out_general(amount_in, reserve, reserve_out, scale, scale)
+            );
+        }
+    }
+
+    #[test]
+    fn test_normalized_reserves() {
+        let mut mixed = MiraV1 {
+            token_0_decimals: 6,
+            token_1_decimals: 18,
+            reserve_0: 5_000_000,
+            reserve_1: 5_000_000_000_000_000_000,
+            ..volatile_pool()
+        };
+        mixed.update_scales();
+        let mut wide = MiraV1 {
+            token_0_decimals: 18,
+            token_1_decimals: 18,
+            reserve_0: 5_000_000_000_000_000_000,
+            reserve_1: 5_000_000_000_000_000_000,
+            ..volatile_pool()
+        };
+        wide.update_scales();
+
+        let five = U256::from(5_000_000_000_000_000_000u64);
+        assert_eq!(mixed.normalized_reserves(), (five, five));
+        assert_eq!(wide.normalized_reserves(), mixed.normalized_reserves());
+    }
+
+    #[test]
+    fn test_calculate_price_zero_reserve() {
+        let pool = MiraV1 {
+            reserve_0: 0,
+            ..volatile_pool()
+        };
+        assert!(matches!(
+            pool.calculate_price(pool.token_0, pool.token_1),
+            Err(ArithmeticError::DivisionByZero)
+        ));
+        assert_eq!(
+            pool.calculate_price(pool.token_1, pool.token_0).unwrap(),
+            0.0
+        );
+
+        let stable = MiraV1 {
+            is_stable: true,
+            ..pool
+        };
+        for base in [stable.token_0, stable.token_1] {
+            assert!(matches!(
+                stable.calculate_price(base, stable.get_token_out(base)),
+                Err(ArithmeticError::DivisionByZero)
+            ));
+        }
+    }
+
+    #[test]
+    fn test_amount_to_rebalance() {
+        let mut pool = MiraV1 {
+            is_stable: true,
+            ..volatile_pool()
+        };
+        let (token_in, amount_in) = pool.amount_to_rebalance().unwrap();
+        assert_eq!(token_in, pool.token_0);
+
+        pool.simulate_swap_mut(token_in, pool.token_1, amount_in)
+            .unwrap();
+        assert!(pool.reserve_0 >= pool.reserve_1);
+        assert!(pool.reserve_0 - pool.reserve_1 < 1_000);
+
+        let (_, balanced) = pool.amount_to_rebalance().unwrap();
+        assert_eq!(balanced, U256::zero());
+
+        assert!(matches!(
+            volatile_pool().amount_to_rebalance(),
+            Err(SwapSimulationError::UnsupportedPool)
+        ));
+    }
+
+    #[test]
+    fn test_reserves_for_price() {
+        let (reserve_0, reserve_1) =
+            reserves_for_price(U256::from(2_000_000_000_000u64), 2.5, 9, 6);
+        assert_eq!(
+            (reserve_0, reserve_1),
+            (400_000_000_000_000, 1_000_000_000_000)
+        );
+
+        let mut pool = MiraV1 {
+            token_0_decimals: 9,
+            token_1_decimals: 6,
+            reserve_0,
+            reserve_1,
+            ..volatile_pool()
+        };
+        pool.update_scales();
+        let price = pool.calculate_price(pool.token_0, pool.token_1).unwrap();
+        assert!((price - 2.5).abs() < 1e-9);
+    }
+
+    #[test]
+    fn test_calculate_price_64_x_64_zero_reserve() {
+        let pool = MiraV1 {
+            reserve_1: 0,
+            ..volatile_pool()
+        };
+
+        assert_eq!(
+            pool.calculate_price_64_x_64(pool.token_1).unwrap(),
+            Q64::MAX
+        );
+        assert_eq!(
+            pool.calculate_price_64_x_64(pool.token_0).unwrap(),
+            Q64::ZERO
+        );
+    }
+
+    #[test]
+    fn test_raw_state() {
+        let pool = volatile_pool();
+        let state = pool.raw_state();
+
+        assert_eq!(state.reserve_0, U256::from(pool.reserve_0));
+        assert_eq!(state.reserve_1, U256::from(pool.reserve_1));
+        assert_eq!(
+            (state.decimals_0, state.decimals_1),
+            (pool.token_0_decimals, pool.token_1_decimals)
+        );
+        assert_eq!(state.fee_bps, 30);
+        assert!(!state.is_stable);
+    }
+
+    #[test]
+    fn test_stable_price_large_reserves() {
+        let deep = MiraV1 {
+            reserve_0: 10_000_000_000_000_000,
+            reserve_1: 10_000_000_000_000_000,
+            is_stable: true,
+            ..volatile_pool()
+        };
+        assert_eq!(
+            deep.calculate_price(deep.token_0, deep.token_1).unwrap(),
+            1.0
+        );
+
+        // 10^18 whole tokens at 0 decimals cube past U256::MAX.
+        let mut too_deep = MiraV1 {
+            token_0_decimals: 0,
+            token_1_decimals: 0,
+            reserve_0: 1_000_000_000_000_000_000,
+            reserve_1: 1_000_000_000_000_000_000,
+            ..deep
+        };
+        too_deep.update_scales();
+        assert!(matches!(
+            too_deep.calculate_price(too_deep.token_0, too_deep.token_1),
+            Err(ArithmeticError::Overflow)
+        ));
+        assert!(matches!(
+            too_deep.stable_price_ratio(too_deep.token_0),
+            Err(ArithmeticError::Overflow)
+        ));
+    }
+
+    #[test]
+    fn test_unknown_decimals_rejects_stable_math() {
+        let (token_0, token_1) = (AssetId::new([1; 32]), AssetId::new([2; 32]));
+        // As enumerated, before `populate_data` fills in the decimals.
+        let mut pool = MiraV1 {
+            pool_id: (token_0, token_1, false),
+            token_0,
+            token_1,
+            reserve_0: 1_000_000_000_000,
+            reserve_1: 1_200_000_000_000,
+            ..Default::default()
+        };
+        assert!(matches!(
+            pool.calculate_price(token_0, token_1),
+            Err(ArithmeticError::DecimalsUnknown)
+        ));
+        pool.is_stable = true;
+        assert!(matches!(
+            pool.calculate_price(token_0, token_1),
+            Err(ArithmeticError::DecimalsUnknown)
+        ));
+        assert!(matches!(
+            pool.simulate_swap(token_0, token_1, U256::from(1_000_000)),
+            Err(SwapSimulationError::DecimalsUnknown)
+        ));
+        assert!(matches!(
+            pool.simulate_swap_mut(token_0, token_1, U256::from(1_000_000)),
+            Err(SwapSimulationError::DecimalsUnknown)
+        ));
+
+        pool.token_0_decimals = 9;
+        pool.token_1_decimals = 9;
+        pool.decimals_known = true;
+        pool.update_scales();
+        assert!(pool.calculate_price(token_0, token_1).is_ok());
+        assert!(pool
+            .simulate_swap(token_0, token_1, U256::from(1_000_000))
+            .is_ok());
+        pool.is_stable = false;
+        assert!((pool.calculate_price(token_0, token_1).unwrap() - 1.2).abs() < 1e-9);
+    }
+
+    #[test]
+    fn test_sim_config_forwarded_to_reads() {
+        use fuels::types::transaction::TxPolicies;
+
+        use crate::amm::mira::reader::{mock::MockReader, PoolReading};
+
+        let mut pool = MiraV1 {
+            sim_config: SimConfig::new(TxPolicies::default().with_script_gas_limit(1_000_000)),
+            ..volatile_pool()
+        };
+        let reader = MockReader {
+            pools: vec![(
+                pool.pool_id,
+                PoolReading {
+                    reserve_0: 5_000,
+                    reserve_1: 6_000,
+                    decimals_0: 9,
+                    decimals_1: 9,
+                },
+            )],
+            fees: pool.fee,
+            ..Default::default()
+        };
+        let runtime = tokio::runtime::Builder::new_current_thread()
+            .build()
+            .unwrap();
+
+        runtime.block_on(async {
+            pool.populate_from(&reader).await.unwrap();
+            pool.sync_from(&reader).await.unwrap();
+        });
+
+        // Two reads to populate and one to sync, all with the pool's policies.
+        let tx_policies = reader.tx_policies.lock().unwrap();
+        assert_eq!(tx_policies.len(), 3);
+        assert!(tx_policies
+            .iter()
+            .all(|policies| policies.script_gas_limit() == Some(1_000_000)));
+        assert_eq!(pool.reserves(), (5_000, 6_000));
+        assert_eq!(
+            pool.sim_config.tx_policies.script_gas_limit(),
+            Some(1_000_000)
+        );
+    }
+
+    #[test]
+    fn test_onchain_balances() {
+        use crate::amm::mira::reader::mock::MockReader;
+
+        let pool = volatile_pool();
+        let reader = MockReader {
+            balances: vec![
+                (pool.token_1, 1_200_000_000_300),
+                (pool.token_0, 1_000_000_000_100),
+            ],
+            ..Default::default()
+        };
+        let runtime = tokio::runtime::Builder::new_current_thread()
+            .build()
+            .unwrap();
+
+        let balances = runtime
+            .block_on(pool.get_onchain_balances_from(&reader))
+            .unwrap();
+        assert_eq!(balances, (1_000_000_000_100, 1_200_000_000_300));
+    }
+
+    #[test]
+    fn test_paused_flag_survives_populate() {
+        use crate::amm::mira::reader::{mock::MockReader, PoolReading};
+
+        let mut pool = MiraV1 {
+            is_paused: true,
+            ..volatile_pool()
+        };
+        let reader = MockReader {
+            pools: vec![(
+                pool.pool_id,
+                PoolReading {
+                    reserve_0: 5_000,
+                    reserve_1: 6_000,
+                    decimals_0: 9,
+                    decimals_1: 9,
+                },
+            )],
+            fees: pool.fee,
+            ..Default::default()
+        };
+        let runtime = tokio::runtime::Builder::new_current_thread()
+            .build()
+            .unwrap();
+
+        runtime.block_on(pool.populate_from(&reader)).unwrap();
+        assert!(pool.is_paused);
+        assert!(matches!(
+            pool.simulate_swap(pool.token_0, pool.token_1, U256::from(1_000)),
+            Err(SwapSimulationError::PoolPaused)
+        ));
+
+        runtime.block_on(pool.sync_from(&reader)).unwrap();
+        assert!(pool.is_paused);
+    }
+
+    #[test]
+    fn test_identical_token_pool_id_rejected() {
+        use fuels::{accounts::wallet::Wallet, types::bech32::Bech32Address};
+
+        let token = AssetId::new([1; 32]);
+        let pool = MiraV1 {
+            pool_id: (token, token, false),
+            ..volatile_pool()
+        };
+        let wallet = Wallet::from_address(Bech32Address::default(), None);
+        let runtime = tokio::runtime::Builder::new_current_thread()
+            .build()
+            .unwrap();
+
+        let result = runtime.block_on(pool.try_get_pool_info(wallet, &SimConfig::default()));
+        assert!(matches!(
+            result,
+            Err(AMMError::Arithmetic(ArithmeticError::InvalidTokenPair))
+        ));
+    }
+
+    #[test]
+    fn test_amount_out_below_reserve_out() {
+        for is_stable in [false, true] {
+            let pool = MiraV1 {
+                is_stable,
+                ..volatile_pool()
+            };
+            let reserve_out = U256::from(pool.reserve_1);
+
+            for amount_in in [1_000u64, 1_000_000_000, 1_000_000_000_000] {
+                let amount_out = pool
+                    .simulate_swap(pool.token_0, pool.token_1, U256::from(amount_in))
+                    .unwrap();
+                assert!(amount_out < reserve_out);
+            }
+        }
+    }
+
+    #[test]
+    fn test_reserves_human() {
+        let mut pool = MiraV1 {
+            token_0_decimals: 6,
+            token_1_decimals: 18,
+            reserve_0: 10_000_000_000,
+            reserve_1: 5_000_000_000_000_000_000,
+            ..volatile_pool()
+        };
+        pool.update_scales();
+
+        assert_eq!(pool.reserves_human(), (10_000.0, 5.0));
+    }
+
+    #[test]
+    fn test_restore_reserves() {
+        let mut pool = volatile_pool();
+        let checkpoint = pool.reserve_checkpoint();
+
+        pool.simulate_swap_mut(pool.token_0, pool.token_1, U256::from(1_000_000_000))
+            .unwrap();
+        assert_ne!(pool.reserve_checkpoint(), checkpoint);
+
+        pool.restore_reserves(checkpoint);
+        assert_eq!(
+            (pool.reserve_0, pool.reserve_1),
+            (volatile_pool().reserve_0, volatile_pool().reserve_1)
+        );
+    }
+
+    #[test]
+    fn test_div_uu_large_numerator() {
+        let two = U256::from(2);
+
+        // Shifting these left by 64 bits would overflow U256.
+        let x = two.pow(U256::from(199)) * U256::from(3) + U256::from(12_345);
+        let y = two.pow(U256::from(150)) * U256::from(7) + U256::from(99);
+        assert_eq!(
+            div_uu(x, y).unwrap(),
+            4_450_540_164_458_423_681_597_568_282_188_653
+        );
+        assert_eq!(
+            div_uu(two.pow(U256::from(200)), two.pow(U256::from(180))).unwrap(),
+            1u128 << 84
+        );
+        assert_eq!(
+            div_uu(U256::max_value(), U256::max_value()).unwrap(),
+            1u128 << 64
+        );
+
+        assert!(matches!(
+            div_uu(two.pow(U256::from(200)), two.pow(U256::from(100))),
+            Err(ArithmeticError::Overflow)
+        ));
+        assert!(matches!(
+            div_uu(two.pow(U256::from(191)), U256::one()),
+            Err(ArithmeticError::Overflow)
+        ));
+    }
+
+    #[test]
+    fn test_paused_pool_rejects_swaps() {
+        let mut pool = MiraV1 {
+            is_paused: true,
+            ..volatile_pool()
+        };
+        let (token_0, token_1) = (pool.token_0, pool.token_1);
+        let amount_in = U256::from(1_000);
+
+        assert!(matches!(
+            pool.simulate_swap(token_0, token_1, amount_in),
+            Err(SwapSimulationError::PoolPaused)
+        ));
+        assert!(matches!(
+            pool.simulate_swap_mut(token_0, token_1, amount_in),
+            Err(SwapSimulationError::PoolPaused)
+        ));
+        assert_eq!(pool.reserve_0, volatile_pool().reserve_0);
+    }
+
+    #[test]
+    fn test_lp_asset_id() {
+        let pool = MiraV1 {
+            address: ContractId::new([9; 32]),
+            ..volatile_pool()
+        };
+        let expected: AssetId =
+            "0x50a89ce332fc07b6bff65e2fe7588fbdd649e7329db3bdce399fa33769a520f3"
+                .parse()
+                .unwrap();
+        assert_eq!(pool.lp_asset_id(), Some(expected));
+
+        let stable = MiraV1 {
+            pool_id: (pool.token_0, pool.token_1, true),
+            ..pool
+        };
+        assert_ne!(stable.lp_asset_id(), pool.lp_asset_id());
+    }
+
+    fn volatile_pool() -> MiraV1 {
+        let token_0 = AssetId::new([1; 32]);
+        let token_1 = AssetId::new([2; 32]);
+        MiraV1 {
+            address: ContractId::zeroed(),
+            pool_id: (token_0, token_1, false),
+            token_0,
+            token_0_decimals: 9,
+            token_1,
+            token_1_decimals: 9,
+            reserve_0: 1_000_000_000_000,
+            reserve_1: 1_200_000_000_000,
+            fee: (300, 50, 0, 0),
+            is_stable: false,
+            scale_0: U256::from(10).pow(U256::from(9)),
+            scale_1: U256::from(10).pow(U256::from(9)),
+            is_paused: false,
+            decimals_known: true,
+            sim_config: SimConfig::default(),
+        }
+    }
+
+    #[cfg(debug_assertions)]
+    #[test]
+    fn test_simulate_swap_mut_preserves_invariant() {
+        for is_stable in [false, true] {
+            let mut pool = MiraV1 {
+                is_stable,
+                ..volatile_pool()
+            };
+            let (token_0, token_1) = (pool.token_0, pool.token_1);
+
+            // Panics in debug builds if the invariant decreases.
+            pool.simulate_swap_mut(token_0, token_1, U256::from(5_000_000_000u64))
+                .unwrap();
+            pool.simulate_swap_mut(token_1, token_0, U256::from(70_000_000_000u64))
+                .unwrap();
+        }
+
+        // The stable solver rounded this output up, decreasing k, before rounding in the
+        // pool's favour.
+        let mut pool = MiraV1 {
+            token_0_decimals: 18,
+            token_1_decimals: 18,
+            reserve_0: 1_000_000_000_000_000_000,
+            reserve_1: 9_000_000_000_000_000_000,
+            is_stable: true,
+            ..volatile_pool()
+        };
+        pool.update_scales();
+        let (token_0, token_1) = (pool.token_0, pool.token_1);
+        assert_eq!(
+            pool.simulate_swap_mut(token_0, token_1, U256::from(400_000_000_000_000u64))
+                .unwrap(),
+            U256::from(1_239_032_230_780_254u64)
+        );
+    }
+
+    #[test]
+    fn test_simulate_swap_mut_checked() {
+        let mut pool = volatile_pool();
+        let amount_in = U256::from(5_000_000_000u64);
+        let expected_out = pool
+            .simulate_swap(pool.token_0, pool.token_1, amount_in)
+            .unwrap();
+
+        let amount_out = pool
+            .simulate_swap_mut_checked(pool.token_0, amount_in, expected_out)
+            .unwrap();
+        assert_eq!(amount_out, expected_out);
+        assert_eq!(pool.reserve_0, 1_005_000_000_000);
+    }
+
+    #[test]
+    fn test_simulate_swap_mut_checked_slippage_exceeded() {
+        let mut pool = volatile_pool();
+        let amount_in = U256::from(5_000_000_000u64);
+        let expected_out = pool
+            .simulate_swap(pool.token_0, pool.token_1, amount_in)
+            .unwrap();
+
+        let result =
+            pool.simulate_swap_mut_checked(pool.token_0, amount_in, expected_out + U256::one());
+        assert!(matches!(result, Err(SwapSimulationError::SlippageExceeded)));
+        assert_eq!(pool.reserve_0, 1_000_000_000_000);
+        assert_eq!(pool.reserve_1, 1_200_000_000_000);
+    }
+
+    #[test]
+    fn test_repair_tokens() {
+        let mut pool = MiraV1 {
+            token_0: AssetId::zeroed(),
+            token_1: AssetId::zeroed(),
+            ..volatile_pool()
+        };
+
+        pool.repair_tokens();
+        assert_eq!(pool.token_0, pool.pool_id.0);
+        assert_eq!(pool.token_1, pool.pool_id.1);
+    }
+
+    #[test]
+    fn test_price_q64_matches_calculate_price() {
+        for is_stable in [false, true] {
+            let pool = MiraV1 {
+                pool_id: (AssetId::new([1; 32]), AssetId::new([2; 32]), is_stable),
+                reserve_0: 1_000_000_000,
+                reserve_1: 1_200_000_000,
+                is_stable,
+                ..volatile_pool()
+            };
+
+            for base_token in [pool.token_0, pool.token_1] {
+                let price = pool
+                    .calculate_price(base_token, pool.get_token_out(base_token))
+                    .unwrap();
+                let price_q64 = pool.price_q64(base_token).unwrap().to_f64();
+                assert!((price_q64 - price).abs() < 1e-9);
+            }
+        }
+    }
+
+    #[test]
+    fn test_stable_price_ratio_matches_calculate_price() {
+        let mut pool = MiraV1 {
+            pool_id: (AssetId::new([1; 32]), AssetId::new([2; 32]), true),
+            token_1_decimals: 6,
+            reserve_0: 1_000_000_000,
+            reserve_1: 1_200_000,
+            is_stable: true,
+            ..volatile_pool()
+        };
+        pool.update_scales();
+
+        for base_token in [pool.token_0, pool.token_1] {
+            let (numerator, denominator) = pool.stable_price_ratio(base_token).unwrap();
+            let ratio = u256_to_f64(numerator * U256::from(10).pow(U256::from(18)) / denominator);
+            let price = pool
+                .calculate_price(base_token, pool.get_token_out(base_token))
+                .unwrap();
+            assert!((ratio - price).abs() <= price * 1e-9);
+        }
+    }
+
+    #[test]
+    fn test_cached_scales() {
+        let token_0 = AssetId::new([1; 32]);
+        let token_1 = AssetId::new([2; 32]);
+        let pool = MiraV1::new(
+            ContractId::zeroed(),
+            (token_0, token_1, false),
+            token_0,
+            18,
+            token_1,
+            6,
+            1_000,
+            1_000,
+            (30, 5, 0, 0),
+            false,
+        );
+
+        assert_eq!(pool.scale_0, U256::from(10).pow(U256::from(18)));
+        assert_eq!(pool.scale_1, U256::from(10).pow(U256::from(6)));
+        assert_eq!(pool.token_scale(0), pool.scale_0);
+        assert_eq!(pool.token_scale(1), pool.scale_1);
+    }
+
+    #[cfg(debug_assertions)]
+    #[test]
+    #[should_panic(expected = "stale scale_1")]
+    fn test_stale_scale_detected() {
+        let mut pool = volatile_pool();
+        pool.token_1_decimals = 6;
+
+        pool.normalized_reserves();
+    }
+
+    #[test]
+    fn test_reserve_ratio_reduced() {
+        let pool = MiraV1 {
+            reserve_0: 4,
+            reserve_1: 8,
+            ..volatile_pool()
+        };
+        assert_eq!(pool.reserve_ratio_reduced(), (1, 2));
+
+        let empty = MiraV1 {
+            reserve_0: 0,
+            reserve_1: 0,
+            ..volatile_pool()
+        };
+        assert_eq!(empty.reserve_ratio_reduced(), (0, 0));
+    }
+
+    /// Outputs of the stable curve `x^3*y + y^3*x = k`, as
+    /// `(amount_in, reserve_in, reserve_out, decimals_in, decimals_out, amount_out)`.
+    ///
+    /// A self-consistency check of the Solidly `f`/`d` formulas used by the solver: each amount
+    /// is the floor of the exact real-valued solution of the curve, computed with arbitrary
+    /// precision outside this crate. They are not taken from Mira's contract or its tests. Fees
+    /// are not applied.
+    const STABLE_CURVE_VALUES: [(u64, u64, u64, u8, u8, u64); 7] = [
+        (
+            1_000_000_000,
+            1_000_000_000_000,
+            1_000_000_000_000,
+            9,
+            9,
+            999_999_999,
+        ),
+        (
+            50_000_000_000,
+            1_000_000_000_000,
+            1_200_000_000_000,
+            9,
+            9,
+            50_035_222_033,
+        ),
+        (1_000_000, 5_000_000_000, 4_000_000_000, 6, 6, 997_252),
+        (
+            1_000_000_000,
+            1_000_000_000_000,
+            1_000_000_000,
+            9,
+            6,
+            999_999,
+        ),
+        (250_000_000, 2_000_000_000_000, 1_900_000_000, 9, 6, 249_991),
+        (
+            123_456_789,
+            987_654_321_000,
+            876_543_210_000,
+            9,
+            9,
+            123_404_344,
+        ),
+        (
+            1_000_000_000_000_000_000,
+            1_000_000_000_000_000_000,
+            1_000_000_000_000_000_000,
+            18,
+            18,
+            753_733_827_832_277_267,
+        ),
+    ];
+
+    #[test]
+    fn test_stable_amount_out_matches_exact_curve() {
+        let pool = MiraV1 {
+            is_stable: true,
+            ..volatile_pool()
+        };
+
+        for (amount_in, reserve_in, reserve_out, decimals_in, decimals_out, expected) in
+            STABLE_CURVE_VALUES
+        {
+            let amount_out = pool.get_stable_amount_out(
+                U256::from(amount_in),
+                U256::from(reserve_in),
+                U256::from(reserve_out),
+                pow10(U256::from(decimals_in)),
+                pow10(U256::from(decimals_out)),
+            );
+            assert_eq!(amount_out, U256::from(expected));
+        }
+    }
+
+    #[cfg(feature = "tracing")]
+    #[test]
+    fn test_on_chain_calls_emit_spans() {
+        use std::sync::{Arc, Mutex};
+
+        use fuels::{accounts::wallet::Wallet, types::bech32::Bech32Address};
+        use tracing::{span, subscriber::with_default, Event, Id, Metadata, Subscriber};
+
+        /// Records the name of every span created while it is the default subscriber.
+        struct SpanRecorder(Arc<Mutex<Vec<&'static str>>>);
+
+        impl Subscriber for SpanRecorder {
+            fn enabled(&self, _metadata: &Metadata<'_>) -> bool {
+                true
+            }
+
+            fn new_span(&self, span: &span::Attributes<'_>) -> Id {
+                let mut spans = self.0.lock().unwrap();
+                spans.push(span.metadata().name());
+                Id::from_u64(spans.len() as u64)
+            }
+
+            fn record(&self, _span: &Id, _values: &span::Record<'_>) {}
+
+            fn record_follows_from(&self, _span: &Id, _follows: &Id) {}
+
+            fn event(&self, _event: &Event<'_>) {}
+
+            fn enter(&self, _span: &Id) {}
+
+            fn exit(&self, _span: &Id) {}
+        }
+
+        let spans = Arc::new(Mutex::new(vec![]));
+        let wallet = Wallet::from_address(Bech32Address::default(), None);
+        let pool = volatile_pool();
+        let sim_config = SimConfig::default();
+        let runtime = tokio::runtime::Builder::new_current_thread()
+            .build()
+            .unwrap();
+
+        with_default(SpanRecorder(spans.clone()), || {
+            runtime.block_on(async {
+                // Without a provider both calls fail before reaching the network.
+                assert!(pool
+                    .try_get_pool_info(wallet.clone(), &sim_config)
+                    .await
+                    .is_err());
+                assert!(pool.get_reserves(wallet, &sim_config).await.is_err());
+            })
+        });
+
+        let spans = spans.lock().unwrap();
+        assert!(spans.contains(&"try_get_pool_info"));
+        assert!(spans.contains(&"get_reserves"));
+    }
 }
@@ -0,0 +1,323 @@
+use async_trait::async_trait;
+use fuels::{
+    accounts::{
+        impersonated_account::ImpersonatedAccount, wallet::Wallet, Account, ViewOnlyAccount,
+    },
+    programs::calls::Execution,
+    types::{
+        bech32::{Bech32Address, Bech32ContractId},
+        AssetId, ContractId,
+    },
+};
+use mira_v1::interface::{MiraAmmContract, PoolId, PoolMetadata};
+
+use crate::{amm::SimConfig, errors::AMMError};
+
+/// The state of a pool as stored by the Mira AMM contract.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct PoolReading {
+    pub reserve_0: u64,
+    pub reserve_1: u64,
+    pub decimals_0: u8,
+    pub decimals_1: u8,
+}
+
+/// Reads the on-chain state of Mira pools.
+///
+/// Implemented for [`Wallet`], which simulates read-only calls against the AMM contract, and for
+/// [`AccountReader`], which also chooses the account the calls run as.
+#[async_trait]
+pub trait MiraReader: Send + Sync {
+    /// Reads the state of `pool_id`, or `None` if the pool does not exist.
+    async fn pool_metadata(
+        &self,
+        contract_id: ContractId,
+        pool_id: PoolId,
+        sim_config: &SimConfig,
+    ) -> Result<Option<PoolReading>, AMMError>;
+
+    /// Reads the `(lp_fee_volatile, lp_fee_stable, protocol_fee_volatile, protocol_fee_stable)`
+    /// fees of the AMM contract.
+    async fn fees(
+        &self,
+        contract_id: ContractId,
+        sim_config: &SimConfig,
+    ) -> Result<(u64, u64, u64, u64), AMMError>;
+
+    /// Reads the balance of `asset_id` held by `contract_id`.
+    async fn contract_balance(
+        &self,
+        contract_id: ContractId,
+        asset_id: AssetId,
+    ) -> Result<u64, AMMError>;
+}
+
+impl From<PoolMetadata> for PoolReading {
+    fn from(metadata: PoolMetadata) -> Self {
+        Self {
+            reserve_0: metadata.reserve_0,
+            reserve_1: metadata.reserve_1,
+            decimals_0: metadata.decimals_0,
+            decimals_1: metadata.decimals_1,
+        }
+    }
+}
+
+/// Reads the state of `pool_id` from the AMM contract at `contract_id`, running as `account`.
+async fn read_pool_metadata<A: Account>(
+    account: A,
+    contract_id: ContractId,
+    pool_id: PoolId,
+    sim_config: &SimConfig,
+) -> Result<Option<PoolReading>, AMMError> {
+    let pool_metadata: Option<PoolMetadata> = MiraAmmContract::new(contract_id, account)
+        .methods()
+        .pool_metadata(pool_id)
+        .with_tx_policies(sim_config.tx_policies)
+        .simulate(Execution::StateReadOnly)
+        .await?
+        .value;
+
+    Ok(pool_metadata.map(PoolReading::from))
+}
+
+/// Reads the fees of the AMM contract at `contract_id`, running as `account`.
+async fn read_fees<A: Account>(
+    account: A,
+    contract_id: ContractId,
+    sim_config: &SimConfig,
+) -> Result<(u64, u64, u64, u64), AMMError> {
+    Ok(MiraAmmContract::new(contract_id, account)
+        .methods()
+        .fees()
+        .with_tx_policies(sim_config.tx_policies)
+        .simulate(Execution::StateReadOnly)
+        .await?
+        .value)
+}
+
+/// Reads simulated as an impersonation of the wallet's own address.
+#[async_trait]
+impl MiraReader for Wallet {
+    async fn pool_metadata(
+        &self,
+        contract_id: ContractId,
+        pool_id: PoolId,
+        sim_config: &SimConfig,
+    ) -> Result<Option<PoolReading>, AMMError> {
+        let account = ImpersonatedAccount::new(self.address().clone(), self.provider().cloned());
+        read_pool_metadata(account, contract_id, pool_id, sim_config).await
+    }
+
+    async fn fees(
+        &self,
+        contract_id: ContractId,
+        sim_config: &SimConfig,
+    ) -> Result<(u64, u64, u64, u64), AMMError> {
+        let account = ImpersonatedAccount::new(self.address().clone(), self.provider().cloned());
+        read_fees(account, contract_id, sim_config).await
+    }
+
+    async fn contract_balance(
+        &self,
+        contract_id: ContractId,
+        asset_id: AssetId,
+    ) -> Result<u64, AMMError> {
+        let provider = self.provider().ok_or(AMMError::MissingProvider)?;
+
+        Ok(provider
+            .get_contract_asset_balance(&Bech32ContractId::from(contract_id), asset_id)
+            .await?)
+    }
+}
+
+/// The account the simulated calls of an [`AccountReader`] run as.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ReadMode {
+    /// Run as an [`ImpersonatedAccount`] of the given address.
+    Impersonate(Bech32Address),
+    /// Run as the reader's own account, for providers that simulate calls from any account.
+    SimulateOnly,
+}
+
+/// Reads pool state through simulated calls made by `account`, as chosen by its [`ReadMode`].
+#[derive(Debug, Clone)]
+pub struct AccountReader<A> {
+    pub account: A,
+    pub mode: ReadMode,
+}
+
+/// The account built for a single read.
+enum ReadAccount<A> {
+    Impersonated(ImpersonatedAccount),
+    Own(A),
+}
+
+impl<A: Account> AccountReader<A> {
+    /// Creates a reader impersonating the address of `account`.
+    pub fn new(account: A) -> Self {
+        let mode = ReadMode::Impersonate(account.address().clone());
+        Self { account, mode }
+    }
+
+    /// Sets the account the simulated calls run as.
+    pub fn with_mode(mut self, mode: ReadMode) -> Self {
+        self.mode = mode;
+        self
+    }
+
+    fn read_account(&self) -> ReadAccount<A> {
+        match &self.mode {
+            ReadMode::Impersonate(address) => ReadAccount::Impersonated(ImpersonatedAccount::new(
+                address.clone(),
+                self.account.try_provider().ok().cloned(),
+            )),
+            ReadMode::SimulateOnly => ReadAccount::Own(self.account.clone()),
+        }
+    }
+}
+
+#[async_trait]
+impl<A: Account> MiraReader for AccountReader<A> {
+    async fn pool_metadata(
+        &self,
+        contract_id: ContractId,
+        pool_id: PoolId,
+        sim_config: &SimConfig,
+    ) -> Result<Option<PoolReading>, AMMError> {
+        match self.read_account() {
+            ReadAccount::Impersonated(account) => {
+                read_pool_metadata(account, contract_id, pool_id, sim_config).await
+            }
+            ReadAccount::Own(account) => {
+                read_pool_metadata(account, contract_id, pool_id, sim_config).await
+            }
+        }
+    }
+
+    async fn fees(
+        &self,
+        contract_id: ContractId,
+        sim_config: &SimConfig,
+    ) -> Result<(u64, u64, u64, u64), AMMError> {
+        match self.read_account() {
+            ReadAccount::Impersonated(account) => read_fees(account, contract_id, sim_config).await,
+            ReadAccount::Own(account) => read_fees(account, contract_id, sim_config).await,
+        }
+    }
+
+    async fn contract_balance(
+        &self,
+        contract_id: ContractId,
+        asset_id: AssetId,
+    ) -> Result<u64, AMMError> {
+        let provider = self
+            .account
+            .try_provider()
+            .map_err(|_| AMMError::MissingProvider)?;
+
+        Ok(provider
+            .get_contract_asset_balance(&Bech32ContractId::from(contract_id), asset_id)
+            .await?)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use fuels::{
+        accounts::{wallet::WalletUnlocked, ViewOnlyAccount},
+        types::bech32::Bech32Address,
+    };
+
+    use super::{AccountReader, ReadAccount, ReadMode};
+
+    #[test]
+    fn test_read_mode() {
+        let wallet = WalletUnlocked::new_random(None);
+        let reader = AccountReader::new(wallet.clone());
+
+        // Impersonating stays the default, as the account's own address.
+        let ReadAccount::Impersonated(account) = reader.read_account() else {
+            panic!("expected an impersonated account");
+        };
+        assert_eq!(account.address(), wallet.address());
+
+        let other = Bech32Address::default();
+        let reader = reader.with_mode(ReadMode::Impersonate(other.clone()));
+        let ReadAccount::Impersonated(account) = reader.read_account() else {
+            panic!("expected an impersonated account");
+        };
+        assert_eq!(account.address(), &other);
+
+        let reader = reader.with_mode(ReadMode::SimulateOnly);
+        let ReadAccount::Own(account) = reader.read_account() else {
+            panic!("expected the reader's own account");
+        };
+        assert_eq!(account.address(), wallet.address());
+    }
+}
+
+#[cfg(test)]
+pub(crate) mod mock {
+    use std::sync::Mutex;
+
+    use async_trait::async_trait;
+    use fuels::types::{transaction::TxPolicies, AssetId, ContractId};
+    use mira_v1::interface::PoolId;
+
+    use super::{MiraReader, PoolReading};
+    use crate::{amm::SimConfig, errors::AMMError};
+
+    /// Serves pool state from memory, recording the policies of every call.
+    #[derive(Debug, Default)]
+    pub(crate) struct MockReader {
+        pub pools: Vec<(PoolId, PoolReading)>,
+        pub fees: (u64, u64, u64, u64),
+        pub balances: Vec<(AssetId, u64)>,
+        pub tx_policies: Mutex<Vec<TxPolicies>>,
+    }
+
+    #[async_trait]
+    impl MiraReader for MockReader {
+        async fn pool_metadata(
+            &self,
+            _contract_id: ContractId,
+            pool_id: PoolId,
+            sim_config: &SimConfig,
+        ) -> Result<Option<PoolReading>, AMMError> {
+            self.tx_policies
+                .lock()
+                .unwrap()
+                .push(sim_config.tx_policies);
+            Ok(self
+                .pools
+                .iter()
+                .find(|(id, _)| *id == pool_id)
+                .map(|(_, reading)| *reading))
+        }
+
+        async fn fees(
+            &self,
+            _contract_id: ContractId,
+            sim_config: &SimConfig,
+        ) -> Result<(u64, u64, u64, u64), AMMError> {
+            self.tx_policies
+                .lock()
+                .unwrap()
+                .push(sim_config.tx_policies);
+            Ok(self.fees)
+        }
+
+        async fn contract_balance(
+            &self,
+            _contract_id: ContractId,
+            asset_id: AssetId,
+        ) -> Result<u64, AMMError> {
+            Ok(self
+                .balances
+                .iter()
+                .find(|(id, _)| *id == asset_id)
+                .map_or(0, |(_, balance)| *balance))
+        }
+    }
+}
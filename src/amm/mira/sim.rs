@@ -0,0 +1,121 @@
+use fuels::types::{AssetId, U256};
+
+use super::MiraV1;
+use crate::errors::SwapSimulationError;
+
+/// A Mira pool with `U256` reserves for multi-step simulations.
+///
+/// Unlike [`MiraV1::simulate_swap_mut`], stepping never narrows the reserves back to `u64`.
+#[derive(Debug, Clone, Copy)]
+pub struct SimPool {
+    pub pool: MiraV1,
+    pub reserve_0: U256,
+    pub reserve_1: U256,
+}
+
+impl SimPool {
+    pub fn new(pool: &MiraV1) -> Self {
+        Self {
+            pool: *pool,
+            reserve_0: U256::from(pool.reserve_0),
+            reserve_1: U256::from(pool.reserve_1),
+        }
+    }
+
+    /// Swaps `amount_in` of `token_in` against the simulated reserves.
+    ///
+    /// Returns the amount out.
+    pub fn step(
+        &mut self,
+        token_in: AssetId,
+        amount_in: U256,
+    ) -> Result<U256, SwapSimulationError> {
+        if self.pool.is_paused {
+            return Err(SwapSimulationError::PoolPaused);
+        }
+
+        let zero_for_one = self.pool.token_0 == token_in;
+        let (reserve_in, reserve_out, decimals_in, decimals_out) = if zero_for_one {
+            (
+                self.reserve_0,
+                self.reserve_1,
+                self.pool.token_0_decimals,
+                self.pool.token_1_decimals,
+            )
+        } else {
+            (
+                self.reserve_1,
+                self.reserve_0,
+                self.pool.token_1_decimals,
+                self.pool.token_0_decimals,
+            )
+        };
+
+        let amount_out = self.pool.checked_amount_out(
+            amount_in,
+            reserve_in,
+            reserve_out,
+            U256::from(decimals_in),
+            U256::from(decimals_out),
+        )?;
+        let reserve_in = reserve_in
+            .checked_add(amount_in)
+            .ok_or(SwapSimulationError::Overflow)?;
+        let reserve_out = reserve_out
+            .checked_sub(amount_out)
+            .ok_or(SwapSimulationError::Overflow)?;
+
+        if zero_for_one {
+            (self.reserve_0, self.reserve_1) = (reserve_in, reserve_out);
+        } else {
+            (self.reserve_1, self.reserve_0) = (reserve_in, reserve_out);
+        }
+
+        Ok(amount_out)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use fuels::types::{AssetId, ContractId, U256};
+
+    use super::SimPool;
+    use crate::amm::{mira::MiraV1, AutomatedMarketMaker};
+
+    #[test]
+    fn test_sim_pool_matches_simulate_swap_mut() {
+        let token_0 = AssetId::new([1; 32]);
+        let token_1 = AssetId::new([2; 32]);
+        let mut pool = MiraV1::new(
+            ContractId::zeroed(),
+            (token_0, token_1, false),
+            token_0,
+            9,
+            token_1,
+            9,
+            1_000_000_000_000,
+            1_200_000_000_000,
+            (300, 50, 0, 0),
+            false,
+        );
+        let mut sim = SimPool::new(&pool);
+
+        let swaps = [
+            (token_0, 1_000_000_000u64),
+            (token_1, 5_000_000_000),
+            (token_0, 123_456_789),
+            (token_1, 1),
+            (token_0, 70_000_000_000),
+        ];
+        for (token_in, amount_in) in swaps {
+            let amount_in = U256::from(amount_in);
+            let expected = pool
+                .simulate_swap_mut(token_in, pool.get_token_out(token_in), amount_in)
+                .unwrap();
+            assert_eq!(sim.step(token_in, amount_in).unwrap(), expected);
+        }
+
+        assert_eq!(sim.reserve_0, U256::from(pool.reserve_0));
+        assert_eq!(sim.reserve_1, U256::from(pool.reserve_1));
+    }
+}
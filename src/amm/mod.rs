@@ -1,20 +1,31 @@
+pub mod cache;
 pub mod consts;
 pub mod factory;
+pub mod fee;
+pub mod mev;
 pub mod mira;
 pub mod oxiswap;
+pub mod price;
+pub mod q64;
+use std::{collections::HashSet, fmt::Debug};
+
 use async_trait::async_trait;
+use consts::DEFAULT_MAX_FEE_BPS;
+use fee::Bps;
 use fuels::{
     accounts::wallet::Wallet,
-    types::{AssetId, ContractId, U256},
+    crypto::Hasher,
+    types::{transaction::TxPolicies, AssetId, ContractId, U256},
 };
-use mira::MiraV1;
+use mira::{u256_to_f64, MiraV1};
+use mira_v1::interface::PoolId;
 use oxiswap::Oxiswap;
 use serde::{Deserialize, Serialize};
 
 use crate::errors::{AMMError, ArithmeticError, SwapSimulationError};
 
 #[async_trait]
-pub trait AutomatedMarketMaker {
+pub trait AutomatedMarketMaker: AutomatedMarketMakerClone + Debug {
     /// Returns the address of the AMM.
     fn address(&self) -> ContractId;
 
@@ -25,12 +36,30 @@ pub trait AutomatedMarketMaker {
     fn tokens(&self) -> Vec<AssetId>;
 
     /// Calculates a f64 representation of base token price in the AMM.
+    ///
+    /// The price is that of `base_token` denominated in the other token of the AMM; the quote
+    /// token is not used. See [`AutomatedMarketMaker::price_oriented`] to pick the orientation.
     fn calculate_price(
         &self,
         base_token: AssetId,
         _quote_token: AssetId,
     ) -> Result<f64, ArithmeticError>;
 
+    /// Calculates the price of `numerator` denominated in `denominator`.
+    ///
+    /// Both tokens must be the pair of the AMM, otherwise `InvalidTokenPair` is returned.
+    fn price_oriented(
+        &self,
+        numerator: AssetId,
+        denominator: AssetId,
+    ) -> Result<f64, ArithmeticError> {
+        if !self.supports_swap(numerator, denominator) {
+            return Err(ArithmeticError::InvalidTokenPair);
+        }
+
+        self.calculate_price(numerator, denominator)
+    }
+
     /// Populates the AMM data via batched static calls.
     async fn populate_data(
         &mut self,
@@ -59,7 +88,329 @@ pub trait AutomatedMarketMaker {
     ) -> Result<U256, SwapSimulationError>;
 
     /// Returns the token out of the AMM for a given `token_in`.
+    ///
+    /// Undefined for AMMs whose two tokens are the same, see [`filter_identical_token_pools`].
     fn get_token_out(&self, token_in: AssetId) -> AssetId;
+
+    /// Returns whether the AMM can swap `token_in` for `token_out`.
+    fn supports_swap(&self, token_in: AssetId, token_out: AssetId) -> bool;
+
+    /// Returns the raw reserves of the AMM, ordered like `tokens()`.
+    fn reserves(&self) -> (u64, u64);
+
+    /// Returns the reserves of the AMM scaled to 18 decimals, ordered like `tokens()`.
+    ///
+    /// Unlike raw reserves, these are comparable across pools of tokens with different decimals.
+    fn normalized_reserves(&self) -> (U256, U256);
+
+    /// Returns the low-level state the AMM simulates swaps from.
+    fn raw_state(&self) -> RawState;
+
+    /// Returns the reserves of the AMM in whole tokens, ordered like `tokens()`.
+    ///
+    /// Each raw reserve is divided by `10^decimals` of its token, for display only.
+    fn reserves_human(&self) -> (f64, f64) {
+        let state = self.raw_state();
+        (
+            state.reserve_0.low_u128() as f64 / 10f64.powi(state.decimals_0.into()),
+            state.reserve_1.low_u128() as f64 / 10f64.powi(state.decimals_1.into()),
+        )
+    }
+
+    /// Returns whether both reserves of the AMM are non-zero.
+    fn has_liquidity(&self) -> bool;
+
+    /// Returns the asset minted to liquidity providers, if the AMM issues one.
+    fn lp_asset_id(&self) -> Option<AssetId>;
+
+    /// Returns how far the AMM is from balanced, from `0` (balanced) to `1` (one reserve empty).
+    ///
+    /// Calculates `|r0 - r1| / (r0 + r1)` over the normalized reserves. An empty AMM counts as
+    /// balanced.
+    fn utilization(&self) -> f64 {
+        let (reserve_0, reserve_1) = self.normalized_reserves();
+        let (reserve_0, reserve_1) = (u256_to_f64(reserve_0), u256_to_f64(reserve_1));
+        if reserve_0 + reserve_1 == 0.0 {
+            return 0.0;
+        }
+
+        (reserve_0 - reserve_1).abs() / (reserve_0 + reserve_1)
+    }
+
+    /// Scores how far the current price can be trusted, from `0` to `1`.
+    ///
+    /// Multiplies a staleness factor `1 - age / max_age`, for an AMM last synced at
+    /// `synced_block`, by a liquidity factor `depth / (depth + half_depth)`, where `depth` is the
+    /// geometric mean of the normalized reserves in whole tokens. Prices older than `max_age`
+    /// blocks, or from an empty AMM, score `0`.
+    fn price_confidence(
+        &self,
+        synced_block: u64,
+        current_block: u64,
+        max_age: u64,
+        half_depth: f64,
+    ) -> f64 {
+        let age = current_block.saturating_sub(synced_block);
+        let freshness = match max_age {
+            0 if age == 0 => 1.0,
+            0 => 0.0,
+            _ => 1.0 - age.min(max_age) as f64 / max_age as f64,
+        };
+
+        let (reserve_0, reserve_1) = self.normalized_reserves();
+        let depth = (u256_to_f64(reserve_0) * u256_to_f64(reserve_1)).sqrt() / 1e18;
+        if depth == 0.0 {
+            return 0.0;
+        }
+
+        freshness * depth / (depth + half_depth.max(0.0))
+    }
+
+    /// Returns a SHA-256 hash of the AMM's tokens, reserves, fee and curve.
+    ///
+    /// Two AMMs in the same economic state share a fingerprint, so comparing fingerprints detects
+    /// state changes. The address is not included.
+    fn state_fingerprint(&self) -> [u8; 32] {
+        let state = self.raw_state();
+        let (reserve_0, reserve_1) = self.reserves();
+        let hasher = self
+            .tokens()
+            .into_iter()
+            .fold(Hasher::default(), |hasher, token| hasher.chain(token));
+        let fingerprint = hasher
+            .chain(reserve_0.to_be_bytes())
+            .chain(reserve_1.to_be_bytes())
+            .chain(state.fee_bps.to_be_bytes())
+            .chain([state.is_stable as u8])
+            .finalize();
+        *fingerprint
+    }
+
+    /// Checks a quote from an external source against the AMM's reserves.
+    ///
+    /// Returns whether `claimed_out` is at most the amount the AMM's formula gives for
+    /// `amount_in` of `token_in`.
+    fn verify_quote(
+        &self,
+        token_in: AssetId,
+        amount_in: U256,
+        claimed_out: U256,
+    ) -> Result<bool, SwapSimulationError> {
+        let computed_out = self.simulate_swap(token_in, self.get_token_out(token_in), amount_in)?;
+        Ok(claimed_out <= computed_out)
+    }
+
+    /// Calculates the smallest input that receives at least `target_out` of `token_out`.
+    ///
+    /// Searches `simulate_swap`, so the result already accounts for the AMM's rounding. Returns
+    /// `InsufficientLiquidity` when no `u64` input reaches `target_out`.
+    fn amount_in_for_exact_out(
+        &self,
+        token_out: AssetId,
+        target_out: U256,
+    ) -> Result<U256, SwapSimulationError> {
+        let token_in = self.get_token_out(token_out);
+        let limit = U256::from(u64::MAX);
+
+        let mut high = U256::one();
+        while self.simulate_swap(token_in, token_out, high)? < target_out {
+            if high >= limit {
+                return Err(SwapSimulationError::InsufficientLiquidity);
+            }
+            high = (high << U256::one()).min(limit);
+        }
+
+        let mut low = U256::zero();
+        while high - low > U256::one() {
+            let mid = low + (high - low) / 2;
+            if self.simulate_swap(token_in, token_out, mid)? >= target_out {
+                high = mid;
+            } else {
+                low = mid;
+            }
+        }
+
+        if self.simulate_swap(token_in, token_out, low)? >= target_out {
+            Ok(low)
+        } else {
+            Ok(high)
+        }
+    }
+
+    /// Calculates the fraction of `amount_in` left after swapping `base_token` out and
+    /// immediately back.
+    ///
+    /// The second swap runs against the state left by the first. A healthy AMM returns slightly
+    /// less than `1 - 2 * fee`, the rest being lost to price impact.
+    fn round_trip_rate(
+        &self,
+        base_token: AssetId,
+        amount_in: U256,
+    ) -> Result<f64, SwapSimulationError> {
+        if amount_in.is_zero() {
+            return Err(SwapSimulationError::DivisionByZero);
+        }
+
+        let quote_token = self.get_token_out(base_token);
+        let mut amm = self.clone_box();
+        let quote_out = amm.simulate_swap_mut(base_token, quote_token, amount_in)?;
+        let base_out = amm.simulate_swap_mut(quote_token, base_token, quote_out)?;
+
+        let one_e18 = U256::from(10).pow(U256::from(18));
+        Ok(u256_to_f64(base_out * one_e18 / amount_in))
+    }
+
+    /// Simulates swapping `amount_in` of `base_token` and deducts `gas_cost_in_out_token`, the
+    /// cost of the swap expressed in the output token, saturating at zero.
+    fn net_output(
+        &self,
+        base_token: AssetId,
+        amount_in: U256,
+        gas_cost_in_out_token: U256,
+    ) -> Result<U256, SwapSimulationError> {
+        let amount_out =
+            self.simulate_swap(base_token, self.get_token_out(base_token), amount_in)?;
+        Ok(amount_out.saturating_sub(gas_cost_in_out_token))
+    }
+
+    /// Calculates the effective bid and ask prices of `base_token` for a trade of `size`.
+    ///
+    /// The bid is the quote received per base when selling `size` of the base token, and the
+    /// ask the quote paid per base when buying `size` of it. Both are simulated against the same
+    /// reserves and quoted per whole token, using the decimals from
+    /// [`AutomatedMarketMaker::raw_state`].
+    fn bid_ask(&self, base_token: AssetId, size: U256) -> Result<(f64, f64), SwapSimulationError> {
+        if size.is_zero() {
+            return Err(SwapSimulationError::DivisionByZero);
+        }
+
+        let quote_token = self.get_token_out(base_token);
+        let quote_out = self.simulate_swap(base_token, quote_token, size)?;
+        let quote_in = self.amount_in_for_exact_out(base_token, size)?;
+
+        let state = self.raw_state();
+        let (base_decimals, quote_decimals) = if self.tokens().first() == Some(&base_token) {
+            (state.decimals_0, state.decimals_1)
+        } else {
+            (state.decimals_1, state.decimals_0)
+        };
+        let scale = 10f64.powi(base_decimals as i32 - quote_decimals as i32);
+
+        let one_e18 = U256::from(10).pow(U256::from(18));
+        let bid = u256_to_f64(quote_out * one_e18 / size) * scale;
+        let ask = u256_to_f64(quote_in * one_e18 / size) * scale;
+        Ok((bid, ask))
+    }
+
+    /// Locally simulates a swap in the AMM, reverting like an on-chain swap when the amount
+    /// received is below `min_out`.
+    /// Mutates the AMM state only if the swap succeeds.
+    fn simulate_swap_mut_checked(
+        &mut self,
+        base_token: AssetId,
+        amount_in: U256,
+        min_out: U256,
+    ) -> Result<U256, SwapSimulationError> {
+        let quote_token = self.get_token_out(base_token);
+        if self.simulate_swap(base_token, quote_token, amount_in)? < min_out {
+            return Err(SwapSimulationError::SlippageExceeded);
+        }
+
+        self.simulate_swap_mut(base_token, quote_token, amount_in)
+    }
+}
+
+/// Clones an AMM behind a trait object.
+///
+/// Implemented for every `Clone` AMM, so boxed AMMs are `Clone` as well.
+pub trait AutomatedMarketMakerClone {
+    /// Returns a boxed copy of the AMM.
+    fn clone_box(&self) -> Box<dyn AutomatedMarketMaker + Send + Sync>;
+}
+
+impl<T: AutomatedMarketMaker + Clone + Send + Sync + 'static> AutomatedMarketMakerClone for T {
+    fn clone_box(&self) -> Box<dyn AutomatedMarketMaker + Send + Sync> {
+        Box::new(self.clone())
+    }
+}
+
+impl Clone for Box<dyn AutomatedMarketMaker> {
+    fn clone(&self) -> Self {
+        self.clone_box()
+    }
+}
+
+impl Clone for Box<dyn AutomatedMarketMaker + Send + Sync> {
+    fn clone(&self) -> Self {
+        self.clone_box()
+    }
+}
+
+/// A uniform low-level view of a pool's state, ordered like `tokens()`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct RawState {
+    pub reserve_0: U256,
+    pub reserve_1: U256,
+    pub decimals_0: u8,
+    pub decimals_1: u8,
+    pub fee_bps: u32,
+    pub is_stable: bool,
+}
+
+/// Configuration for the simulated calls used to read on-chain state.
+#[derive(Debug, Clone, Copy)]
+pub struct SimConfig {
+    pub tx_policies: TxPolicies,
+    // Pools read with a higher fee are rejected as corrupted
+    pub max_fee: Bps,
+}
+
+impl Default for SimConfig {
+    fn default() -> Self {
+        Self::new(TxPolicies::default())
+    }
+}
+
+impl SimConfig {
+    /// Creates a new configuration using `tx_policies` for every simulated call.
+    pub fn new(tx_policies: TxPolicies) -> Self {
+        Self {
+            tx_policies,
+            max_fee: Bps::from_bps(DEFAULT_MAX_FEE_BPS),
+        }
+    }
+
+    /// Sets the highest pool fee accepted when reading pool state.
+    pub fn with_max_fee(mut self, max_fee: Bps) -> Self {
+        self.max_fee = max_fee;
+        self
+    }
+}
+
+/// Uniquely identifies a pool, including pools that share a contract.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub struct PoolKey {
+    pub address: ContractId,
+    pub pool_id: Option<PoolId>,
+}
+
+/// The reserves of a single pool, small enough to broadcast on every block.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ReserveSnapshot {
+    pub address: ContractId,
+    pub pool_id: Option<PoolId>,
+    pub reserve_0: u64,
+    pub reserve_1: u64,
+}
+
+impl ReserveSnapshot {
+    /// Returns the key of the pool this snapshot belongs to.
+    pub fn pool_key(&self) -> PoolKey {
+        PoolKey {
+            address: self.address,
+            pool_id: self.pool_id,
+        }
+    }
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -67,6 +418,9 @@ pub trait AutomatedMarketMaker {
 pub enum AMM {
     Oxiswap(Oxiswap),
     MiraV1(MiraV1),
+    /// A pool type supplied by another crate. Custom pools cannot be serialized.
+    #[serde(skip)]
+    Custom(Box<dyn AutomatedMarketMaker + Send + Sync>),
 }
 
 #[async_trait]
@@ -75,6 +429,7 @@ impl AutomatedMarketMaker for AMM {
         match self {
             AMM::Oxiswap(pool) => pool.address,
             AMM::MiraV1(pool) => pool.address,
+            AMM::Custom(pool) => pool.address(),
         }
     }
 
@@ -82,6 +437,7 @@ impl AutomatedMarketMaker for AMM {
         match self {
             AMM::Oxiswap(pool) => pool.sync(wallet).await,
             AMM::MiraV1(pool) => pool.sync(wallet).await,
+            AMM::Custom(pool) => pool.sync(wallet).await,
         }
     }
 
@@ -89,6 +445,7 @@ impl AutomatedMarketMaker for AMM {
         match self {
             AMM::Oxiswap(pool) => pool.tokens(),
             AMM::MiraV1(pool) => pool.tokens(),
+            AMM::Custom(pool) => pool.tokens(),
         }
     }
 
@@ -100,6 +457,7 @@ impl AutomatedMarketMaker for AMM {
         match self {
             AMM::Oxiswap(pool) => pool.calculate_price(base_token, _quote_token),
             AMM::MiraV1(pool) => pool.calculate_price(base_token, _quote_token),
+            AMM::Custom(pool) => pool.calculate_price(base_token, _quote_token),
         }
     }
 
@@ -111,6 +469,7 @@ impl AutomatedMarketMaker for AMM {
         match self {
             AMM::Oxiswap(pool) => pool.populate_data(block_number, wallet).await,
             AMM::MiraV1(pool) => pool.populate_data(block_number, wallet).await,
+            AMM::Custom(pool) => pool.populate_data(block_number, wallet).await,
         }
     }
 
@@ -123,6 +482,7 @@ impl AutomatedMarketMaker for AMM {
         match self {
             AMM::Oxiswap(pool) => pool.simulate_swap(base_token, _quote_token, amount_in),
             AMM::MiraV1(pool) => pool.simulate_swap(base_token, _quote_token, amount_in),
+            AMM::Custom(pool) => pool.simulate_swap(base_token, _quote_token, amount_in),
         }
     }
 
@@ -135,6 +495,7 @@ impl AutomatedMarketMaker for AMM {
         match self {
             AMM::Oxiswap(pool) => pool.simulate_swap_mut(base_token, _quote_token, amount_in),
             AMM::MiraV1(pool) => pool.simulate_swap_mut(base_token, _quote_token, amount_in),
+            AMM::Custom(pool) => pool.simulate_swap_mut(base_token, _quote_token, amount_in),
         }
     }
 
@@ -142,6 +503,1478 @@ impl AutomatedMarketMaker for AMM {
         match self {
             AMM::Oxiswap(pool) => pool.get_token_out(base_token),
             AMM::MiraV1(pool) => pool.get_token_out(base_token),
+            AMM::Custom(pool) => pool.get_token_out(base_token),
+        }
+    }
+
+    fn supports_swap(&self, token_in: AssetId, token_out: AssetId) -> bool {
+        match self {
+            AMM::Oxiswap(pool) => pool.supports_swap(token_in, token_out),
+            AMM::MiraV1(pool) => pool.supports_swap(token_in, token_out),
+            AMM::Custom(pool) => pool.supports_swap(token_in, token_out),
+        }
+    }
+
+    fn reserves(&self) -> (u64, u64) {
+        match self {
+            AMM::Oxiswap(pool) => pool.reserves(),
+            AMM::MiraV1(pool) => pool.reserves(),
+            AMM::Custom(pool) => pool.reserves(),
+        }
+    }
+
+    fn normalized_reserves(&self) -> (U256, U256) {
+        match self {
+            AMM::Oxiswap(pool) => pool.normalized_reserves(),
+            AMM::MiraV1(pool) => pool.normalized_reserves(),
+            AMM::Custom(pool) => pool.normalized_reserves(),
+        }
+    }
+
+    fn raw_state(&self) -> RawState {
+        match self {
+            AMM::Oxiswap(pool) => pool.raw_state(),
+            AMM::MiraV1(pool) => pool.raw_state(),
+            AMM::Custom(pool) => pool.raw_state(),
+        }
+    }
+
+    fn has_liquidity(&self) -> bool {
+        match self {
+            AMM::Oxiswap(pool) => pool.has_liquidity(),
+            AMM::MiraV1(pool) => pool.has_liquidity(),
+            AMM::Custom(pool) => pool.has_liquidity(),
+        }
+    }
+
+    fn lp_asset_id(&self) -> Option<AssetId> {
+        match self {
+            AMM::Oxiswap(pool) => pool.lp_asset_id(),
+            AMM::MiraV1(pool) => pool.lp_asset_id(),
+            AMM::Custom(pool) => pool.lp_asset_id(),
+        }
+    }
+}
+
+impl AMM {
+    /// Returns the key uniquely identifying this pool.
+    pub fn pool_key(&self) -> PoolKey {
+        match self {
+            AMM::Oxiswap(pool) => PoolKey {
+                address: pool.address,
+                pool_id: None,
+            },
+            AMM::MiraV1(pool) => PoolKey {
+                address: pool.address,
+                pool_id: Some(pool.pool_id),
+            },
+            AMM::Custom(pool) => PoolKey {
+                address: pool.address(),
+                pool_id: None,
+            },
+        }
+    }
+
+    /// Returns the swap fee of the pool, or `None` for custom pools.
+    pub fn fee_bps(&self) -> Option<Bps> {
+        match self {
+            AMM::Oxiswap(pool) => Some(pool.fee_bps()),
+            AMM::MiraV1(pool) => Some(pool.fee_bps()),
+            AMM::Custom(_) => None,
+        }
+    }
+
+    /// Returns the spot price of `base_token` as an exact `(numerator, denominator)` ratio.
+    ///
+    /// The price is per whole token, so it accounts for the token decimals. Returns `None` for
+    /// custom pools, and when the price is undefined.
+    pub fn spot_price_ratio(&self, base_token: AssetId) -> Option<(U256, U256)> {
+        let (numerator, denominator) = match self {
+            AMM::MiraV1(pool) if pool.is_stable => pool.stable_price_ratio(base_token).ok()?,
+            AMM::MiraV1(pool) if !pool.decimals_known => return None,
+            AMM::Oxiswap(_) | AMM::MiraV1(_) => {
+                let state = self.raw_state();
+                let value_0 = state.reserve_0 * U256::from(10).pow(U256::from(state.decimals_1));
+                let value_1 = state.reserve_1 * U256::from(10).pow(U256::from(state.decimals_0));
+                if self.tokens()[0] == base_token {
+                    (value_1, value_0)
+                } else {
+                    (value_0, value_1)
+                }
+            }
+            AMM::Custom(_) => return None,
+        };
+
+        if denominator.is_zero() {
+            return None;
+        }
+        Some((numerator, denominator))
+    }
+
+    /// Returns the current reserves of the pool, ordered like `tokens()`.
+    pub fn reserve_snapshot(&self) -> ReserveSnapshot {
+        let key = self.pool_key();
+        let (reserve_0, reserve_1) = self.reserves();
+        ReserveSnapshot {
+            address: key.address,
+            pool_id: key.pool_id,
+            reserve_0,
+            reserve_1,
+        }
+    }
+
+    /// Overwrites the reserves of the pool with those in `snap`.
+    ///
+    /// Snapshots taken from a different pool, or applied to a custom pool, are ignored.
+    pub fn apply_reserve_snapshot(&mut self, snap: &ReserveSnapshot) {
+        if snap.pool_key() != self.pool_key() {
+            return;
+        }
+
+        match self {
+            AMM::Oxiswap(pool) => {
+                pool.reserve_a = snap.reserve_0;
+                pool.reserve_b = snap.reserve_1;
+            }
+            AMM::MiraV1(pool) => {
+                pool.reserve_0 = snap.reserve_0;
+                pool.reserve_1 = snap.reserve_1;
+            }
+            AMM::Custom(_) => {}
+        }
+    }
+
+    /// Simulates a swap as if `add_0` and `add_1` had been added to the reserves, ordered like
+    /// `tokens()`. The pool itself is left untouched.
+    pub fn simulate_swap_with_added_liquidity(
+        &self,
+        base_token: AssetId,
+        amount_in: U256,
+        add_0: u64,
+        add_1: u64,
+    ) -> Result<U256, SwapSimulationError> {
+        if let AMM::Custom(_) = self {
+            return Err(SwapSimulationError::UnsupportedPool);
+        }
+
+        let mut snap = self.reserve_snapshot();
+        snap.reserve_0 = snap
+            .reserve_0
+            .checked_add(add_0)
+            .ok_or(SwapSimulationError::Overflow)?;
+        snap.reserve_1 = snap
+            .reserve_1
+            .checked_add(add_1)
+            .ok_or(SwapSimulationError::Overflow)?;
+
+        let mut pool = self.clone();
+        pool.apply_reserve_snapshot(&snap);
+        pool.simulate_swap(base_token, pool.get_token_out(base_token), amount_in)
+    }
+
+    /// Calculates the price of `token` after `donated` of it is added to its reserve without a
+    /// swap. The pool itself is left untouched.
+    pub fn price_after_donation(
+        &self,
+        token: AssetId,
+        donated: u64,
+    ) -> Result<f64, ArithmeticError> {
+        if let AMM::Custom(_) = self {
+            return Err(ArithmeticError::UnsupportedPool);
+        }
+
+        let mut snap = self.reserve_snapshot();
+        let tokens = self.tokens();
+        let reserve = if tokens[0] == token {
+            &mut snap.reserve_0
+        } else if tokens[1] == token {
+            &mut snap.reserve_1
+        } else {
+            return Err(ArithmeticError::InvalidTokenPair);
+        };
+        *reserve = reserve
+            .checked_add(donated)
+            .ok_or(ArithmeticError::Overflow)?;
+
+        let mut pool = self.clone();
+        pool.apply_reserve_snapshot(&snap);
+        pool.calculate_price(token, pool.get_token_out(token))
+    }
+
+    /// Calculates the price of `base` the pool would quote if its reserves were `reserve_0` and
+    /// `reserve_1`, in the order of [`AutomatedMarketMaker::tokens`]. The pool itself is left
+    /// untouched.
+    pub fn price_at_ratio(
+        &self,
+        base: AssetId,
+        reserve_0: u64,
+        reserve_1: u64,
+    ) -> Result<f64, ArithmeticError> {
+        if let AMM::Custom(_) = self {
+            return Err(ArithmeticError::UnsupportedPool);
+        }
+        if !self.tokens().contains(&base) {
+            return Err(ArithmeticError::InvalidTokenPair);
+        }
+
+        let mut snap = self.reserve_snapshot();
+        snap.reserve_0 = reserve_0;
+        snap.reserve_1 = reserve_1;
+
+        let mut pool = self.clone();
+        pool.apply_reserve_snapshot(&snap);
+        pool.calculate_price(base, pool.get_token_out(base))
+    }
+
+    /// Returns whether swapping `amount_in` of `token_in` with a minimum output of `min_out`
+    /// would revert on chain. The pool itself is left untouched.
+    ///
+    /// Runs [`AutomatedMarketMaker::simulate_swap_mut_checked`] on a copy of the pool. A swap
+    /// reverts when it falls short of `min_out`, pays out nothing, exceeds the liquidity of the
+    /// pool, or the pool is paused. Any other simulation error is returned.
+    pub fn would_revert(
+        &self,
+        token_in: AssetId,
+        amount_in: U256,
+        min_out: U256,
+    ) -> Result<bool, SwapSimulationError> {
+        // On-chain amounts are u64, which the simulated reserves are updated with.
+        if amount_in > U256::from(u64::MAX) {
+            return Err(SwapSimulationError::Overflow);
+        }
+
+        let mut pool = self.clone();
+        match pool.simulate_swap_mut_checked(token_in, amount_in, min_out) {
+            Ok(amount_out) => Ok(amount_out.is_zero()),
+            Err(
+                SwapSimulationError::SlippageExceeded
+                | SwapSimulationError::InsufficientLiquidity
+                | SwapSimulationError::PoolPaused,
+            ) => Ok(true),
+            Err(err) => Err(err),
+        }
+    }
+
+    /// Returns whether the pool lives at `address`.
+    pub fn matches_address(&self, address: ContractId) -> bool {
+        self.address() == address
+    }
+
+    /// Returns whether this is the Mira pool identified by `pool_id`.
+    ///
+    /// Mira pools share a single contract, so the address alone does not identify them.
+    pub fn matches_pool(&self, pool_id: &PoolId) -> bool {
+        match self {
+            AMM::Oxiswap(_) | AMM::Custom(_) => false,
+            AMM::MiraV1(pool) => pool.pool_id == *pool_id,
         }
     }
+
+    /// Checks that the state of the pool is one a healthy pool can be in.
+    ///
+    /// Rejects pools that do not trade two distinct tokens, have token decimals above 18, or
+    /// charge a fee of 100% or more, as returned by a pool whose reads have gone wrong.
+    pub fn validate_state(&self) -> Result<(), AMMError> {
+        let tokens = self.tokens();
+        if tokens.len() != 2 || tokens[0] == tokens[1] {
+            return Err(AMMError::InvalidState(
+                "pool does not trade two distinct tokens",
+            ));
+        }
+
+        let state = self.raw_state();
+        if state.decimals_0 > 18 || state.decimals_1 > 18 {
+            return Err(AMMError::InvalidState("token decimals above 18"));
+        }
+        if state.fee_bps >= Bps::DENOMINATOR {
+            return Err(AMMError::InvalidState("fee takes the whole input"));
+        }
+
+        Ok(())
+    }
+}
+
+/// Returns the distinct tokens across `amms`, in the order they are first seen.
+pub fn distinct_tokens(amms: &[AMM]) -> Vec<AssetId> {
+    let mut seen = HashSet::new();
+    amms.iter()
+        .flat_map(|amm| amm.tokens())
+        .filter(|token| seen.insert(*token))
+        .collect()
+}
+
+/// Calculates each pool's share of the `base` liquidity across all pools trading `base` for
+/// `quote`.
+///
+/// Shares are taken over normalized reserves, so they sum to 1 unless every matching pool is
+/// empty, in which case they are all zero.
+pub fn liquidity_shares(amms: &[AMM], base: AssetId, quote: AssetId) -> Vec<(ContractId, f64)> {
+    let reserves: Vec<(ContractId, f64)> = amms
+        .iter()
+        .filter(|amm| amm.supports_swap(base, quote))
+        .map(|amm| {
+            let (reserve_0, reserve_1) = amm.normalized_reserves();
+            let reserve = if amm.tokens()[0] == base {
+                reserve_0
+            } else {
+                reserve_1
+            };
+            (amm.address(), u256_to_f64(reserve))
+        })
+        .collect();
+
+    let total: f64 = reserves.iter().map(|(_, reserve)| reserve).sum();
+    reserves
+        .into_iter()
+        .map(|(address, reserve)| {
+            let share = if total == 0.0 { 0.0 } else { reserve / total };
+            (address, share)
+        })
+        .collect()
+}
+
+/// Removes duplicate pools, keeping the first occurrence of each [`PoolKey`].
+pub fn dedup_amms(amms: Vec<AMM>) -> Vec<AMM> {
+    let mut seen = HashSet::new();
+    amms.into_iter()
+        .filter(|amm| seen.insert(amm.pool_key()))
+        .collect()
+}
+
+/// Removes pools with an empty reserve, which cannot quote or route swaps.
+pub fn filter_amms_with_empty_reserves(amms: Vec<AMM>) -> Vec<AMM> {
+    amms.into_iter().filter(|amm| amm.has_liquidity()).collect()
+}
+
+/// Compounds the fees of the hops `route` (indices into `amms`) into one effective fee in bps.
+///
+/// Calculates `1 - Π(1 - fee_i)`, rounding the fee up at each hop. Pools without a known fee
+/// count as free.
+pub fn route_total_fee_bps(route: &[usize], amms: &[AMM]) -> u32 {
+    let denominator = Bps::RAW_DENOMINATOR as u64;
+    let remaining = route.iter().fold(denominator, |remaining, &i| {
+        let complement = amms[i]
+            .fee_bps()
+            .map_or(denominator, |fee| fee.complement_raw() as u64);
+        remaining * complement / denominator
+    });
+
+    (denominator - remaining).div_ceil(10) as u32
+}
+
+/// Calculates the geometric mean of the hop prices along `route` (indices into `amms`).
+///
+/// Each hop is priced in the direction of travel, starting from the token of the first pool not
+/// shared with the second. Returns `None` for an empty or disconnected route, or if any hop has
+/// no positive price.
+pub fn route_geomean_price(route: &[usize], amms: &[AMM]) -> Option<f64> {
+    let first = amms.get(*route.first()?)?.tokens();
+    let mut token_in = match route.get(1) {
+        Some(&next) => {
+            let next = amms.get(next)?.tokens();
+            *first.iter().find(|token| !next.contains(token))?
+        }
+        None => *first.first()?,
+    };
+
+    let mut log_sum = 0.0;
+    for &i in route {
+        let amm = amms.get(i)?;
+        if !amm.tokens().contains(&token_in) {
+            return None;
+        }
+        let token_out = amm.get_token_out(token_in);
+        let price = amm.calculate_price(token_in, token_out).ok()?;
+        if price.is_nan() || price <= 0.0 {
+            return None;
+        }
+
+        log_sum += price.ln();
+        token_in = token_out;
+    }
+
+    Some((log_sum / route.len() as f64).exp())
+}
+
+/// Splits `amount_in` of `token_in` across `pools` to maximize the total output.
+///
+/// Allocates `amount_in` in `iterations` equal chunks, each to the pool whose output grows the
+/// most from it, so the marginal prices of the pools converge as `iterations` increases. The
+/// division remainder goes to the last chunk, and a chunk no pool can quote goes to the pool
+/// already holding the most, so the allocations sum to `amount_in` whenever any pool trades
+/// `token_in`. Returns the allocation of each pool, in order.
+pub fn waterfill_split(
+    pools: &[&AMM],
+    token_in: AssetId,
+    amount_in: U256,
+    iterations: usize,
+) -> Vec<U256> {
+    let mut allocation = vec![U256::zero(); pools.len()];
+    if iterations == 0 {
+        return allocation;
+    }
+
+    let output = |pool: &AMM, amount| {
+        pool.simulate_swap(token_in, pool.get_token_out(token_in), amount)
+            .ok()
+    };
+    let chunk = amount_in / U256::from(iterations);
+    for iteration in 0..iterations {
+        let chunk = if iteration + 1 == iterations {
+            amount_in - chunk * U256::from(iterations - 1)
+        } else {
+            chunk
+        };
+
+        let supporting = pools
+            .iter()
+            .enumerate()
+            .filter(|(_, pool)| pool.tokens().contains(&token_in));
+        let best = supporting
+            .clone()
+            .filter_map(|(i, pool)| {
+                let gain = output(pool, allocation[i] + chunk)?
+                    .checked_sub(output(pool, allocation[i])?)?;
+                Some((i, gain))
+            })
+            .max_by_key(|(_, gain)| *gain)
+            .map(|(i, _)| i)
+            .or_else(|| {
+                // `max_by_key` keeps the last maximum, so reverse to prefer the first pool
+                supporting
+                    .rev()
+                    .max_by_key(|(i, _)| allocation[*i])
+                    .map(|(i, _)| i)
+            });
+        if let Some(i) = best {
+            allocation[i] += chunk;
+        }
+    }
+
+    allocation
+}
+
+/// Calculates the output of a constant product swap charging `fee_bps` on the input.
+///
+/// Fees above 100% leave nothing to swap, so the output is zero.
+pub fn cp_amount_out(
+    amount_in: U256,
+    reserve_in: U256,
+    reserve_out: U256,
+    fee_bps: u32,
+) -> Result<U256, SwapSimulationError> {
+    let amount_in_with_fee = amount_in
+        .checked_mul(U256::from(Bps::from_bps(fee_bps).complement_raw()))
+        .ok_or(SwapSimulationError::Overflow)?;
+    let numerator = amount_in_with_fee
+        .checked_mul(reserve_out)
+        .ok_or(SwapSimulationError::Overflow)?;
+    let denominator = reserve_in
+        .checked_mul(U256::from(Bps::RAW_DENOMINATOR))
+        .and_then(|reserve_in| reserve_in.checked_add(amount_in_with_fee))
+        .ok_or(SwapSimulationError::Overflow)?;
+
+    numerator
+        .checked_div(denominator)
+        .ok_or(SwapSimulationError::DivisionByZero)
+}
+
+/// Removes pools whose two tokens are the same, which a corrupted pool id can produce.
+pub fn filter_identical_token_pools(amms: Vec<AMM>) -> Vec<AMM> {
+    amms.into_iter()
+        .filter(|amm| {
+            let tokens = amm.tokens();
+            tokens.len() != 2 || tokens[0] != tokens[1]
+        })
+        .collect()
+}
+
+/// Reduces the ratio `a : b` to lowest terms.
+pub(crate) fn reduce_ratio(a: u64, b: u64) -> (u64, u64) {
+    let (mut x, mut y) = (a, b);
+    while y != 0 {
+        (x, y) = (y, x % y);
+    }
+
+    if x == 0 {
+        (a, b)
+    } else {
+        (a / x, b / x)
+    }
+}
+
+/// Repairs Mira pools whose tokens were left zeroed, re-deriving them from the pool id.
+pub fn repair_amms(amms: &mut [AMM]) {
+    for amm in amms.iter_mut() {
+        if let AMM::MiraV1(pool) = amm {
+            pool.repair_tokens();
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use async_trait::async_trait;
+    use fuels::{
+        accounts::wallet::Wallet,
+        types::{transaction::TxPolicies, AssetId, ContractId, U256},
+    };
+
+    use super::{
+        cp_amount_out, dedup_amms, distinct_tokens, fee::Bps, filter_amms_with_empty_reserves,
+        filter_identical_token_pools, liquidity_shares, mira::MiraV1, oxiswap::Oxiswap,
+        route_geomean_price, route_total_fee_bps, waterfill_split, AutomatedMarketMaker, RawState,
+        SimConfig, AMM,
+    };
+    use crate::{
+        errors::{AMMError, ArithmeticError, SwapSimulationError},
+        quote,
+    };
+
+    /// Swaps one for one, up to its reserve of the output token.
+    #[derive(Debug, Clone)]
+    struct PegPool {
+        token_a: AssetId,
+        token_b: AssetId,
+        reserve_a: u64,
+        reserve_b: u64,
+    }
+
+    #[async_trait]
+    impl AutomatedMarketMaker for PegPool {
+        fn address(&self) -> ContractId {
+            ContractId::new([7; 32])
+        }
+
+        async fn sync(&mut self, _wallet: Wallet) -> Result<(), AMMError> {
+            Ok(())
+        }
+
+        fn tokens(&self) -> Vec<AssetId> {
+            vec![self.token_a, self.token_b]
+        }
+
+        fn calculate_price(
+            &self,
+            _base_token: AssetId,
+            _quote_token: AssetId,
+        ) -> Result<f64, ArithmeticError> {
+            Ok(1.0)
+        }
+
+        async fn populate_data(
+            &mut self,
+            _block_number: Option<u64>,
+            _wallet: Wallet,
+        ) -> Result<(), AMMError> {
+            Ok(())
+        }
+
+        fn simulate_swap(
+            &self,
+            base_token: AssetId,
+            _quote_token: AssetId,
+            amount_in: U256,
+        ) -> Result<U256, SwapSimulationError> {
+            let reserve_out = if base_token == self.token_a {
+                self.reserve_b
+            } else {
+                self.reserve_a
+            };
+            Ok(amount_in.min(U256::from(reserve_out)))
+        }
+
+        fn simulate_swap_mut(
+            &mut self,
+            base_token: AssetId,
+            quote_token: AssetId,
+            amount_in: U256,
+        ) -> Result<U256, SwapSimulationError> {
+            let amount_out = self.simulate_swap(base_token, quote_token, amount_in)?;
+            let (reserve_in, reserve_out) = if base_token == self.token_a {
+                (&mut self.reserve_a, &mut self.reserve_b)
+            } else {
+                (&mut self.reserve_b, &mut self.reserve_a)
+            };
+            *reserve_in += amount_in.as_u64();
+            *reserve_out -= amount_out.as_u64();
+            Ok(amount_out)
+        }
+
+        fn get_token_out(&self, token_in: AssetId) -> AssetId {
+            if token_in == self.token_a {
+                self.token_b
+            } else {
+                self.token_a
+            }
+        }
+
+        fn supports_swap(&self, token_in: AssetId, token_out: AssetId) -> bool {
+            self.tokens().contains(&token_in)
+                && self.tokens().contains(&token_out)
+                && token_in != token_out
+        }
+
+        fn reserves(&self) -> (u64, u64) {
+            (self.reserve_a, self.reserve_b)
+        }
+
+        fn normalized_reserves(&self) -> (U256, U256) {
+            (U256::from(self.reserve_a), U256::from(self.reserve_b))
+        }
+
+        fn raw_state(&self) -> RawState {
+            RawState {
+                reserve_0: U256::from(self.reserve_a),
+                reserve_1: U256::from(self.reserve_b),
+                decimals_0: 18,
+                decimals_1: 18,
+                fee_bps: 0,
+                is_stable: false,
+            }
+        }
+
+        fn has_liquidity(&self) -> bool {
+            self.reserve_a != 0 && self.reserve_b != 0
+        }
+
+        fn lp_asset_id(&self) -> Option<AssetId> {
+            None
+        }
+    }
+
+    fn oxiswap_pool(token_a: AssetId, token_b: AssetId) -> AMM {
+        AMM::Oxiswap(Oxiswap::new(
+            ContractId::zeroed(),
+            token_a,
+            token_b,
+            1_000_000,
+            1_000_000,
+            300,
+        ))
+    }
+
+    #[test]
+    fn test_distinct_tokens() {
+        let token_a = AssetId::new([1; 32]);
+        let token_b = AssetId::new([2; 32]);
+        let token_c = AssetId::new([3; 32]);
+        let amms = vec![
+            oxiswap_pool(token_a, token_b),
+            oxiswap_pool(token_b, token_c),
+            oxiswap_pool(token_c, token_a),
+        ];
+
+        assert_eq!(distinct_tokens(&amms), vec![token_a, token_b, token_c]);
+    }
+
+    #[test]
+    fn test_matches_address_and_pool() {
+        let token_a = AssetId::new([1; 32]);
+        let token_b = AssetId::new([2; 32]);
+        let mira_address = ContractId::new([9; 32]);
+        let volatile = AMM::MiraV1(MiraV1 {
+            address: mira_address,
+            pool_id: (token_a, token_b, false),
+            ..Default::default()
+        });
+        let stable = AMM::MiraV1(MiraV1 {
+            address: mira_address,
+            pool_id: (token_a, token_b, true),
+            ..Default::default()
+        });
+        let oxiswap = oxiswap_pool(token_a, token_b);
+
+        assert!(volatile.matches_address(mira_address));
+        assert!(stable.matches_address(mira_address));
+        assert!(!oxiswap.matches_address(mira_address));
+
+        assert!(volatile.matches_pool(&(token_a, token_b, false)));
+        assert!(!stable.matches_pool(&(token_a, token_b, false)));
+        assert!(!oxiswap.matches_pool(&(token_a, token_b, false)));
+    }
+
+    #[test]
+    fn test_sim_config_forwards_tx_policies() {
+        let tx_policies = TxPolicies::default().with_script_gas_limit(1_000_000);
+        let sim_config = SimConfig::new(tx_policies);
+
+        assert_eq!(sim_config.tx_policies.script_gas_limit(), Some(1_000_000));
+        assert_eq!(SimConfig::default().tx_policies.script_gas_limit(), None);
+        assert_eq!(SimConfig::default().max_fee, Bps::from_bps(1000));
+    }
+
+    #[test]
+    fn test_dedup_amms() {
+        let token_a = AssetId::new([1; 32]);
+        let token_b = AssetId::new([2; 32]);
+        let mira = |is_stable| {
+            AMM::MiraV1(MiraV1 {
+                pool_id: (token_a, token_b, is_stable),
+                ..Default::default()
+            })
+        };
+        let amms = vec![
+            mira(false),
+            mira(true),
+            oxiswap_pool(token_a, token_b),
+            mira(false),
+            oxiswap_pool(token_a, token_b),
+        ];
+
+        let deduped = dedup_amms(amms);
+        assert_eq!(deduped.len(), 3);
+        assert!(deduped[0].matches_pool(&(token_a, token_b, false)));
+        assert!(deduped[1].matches_pool(&(token_a, token_b, true)));
+        assert!(matches!(deduped[2], AMM::Oxiswap(_)));
+    }
+
+    #[test]
+    fn test_route_total_fee_bps() {
+        let token_a = AssetId::new([1; 32]);
+        let token_b = AssetId::new([2; 32]);
+        let token_c = AssetId::new([3; 32]);
+        let amms = vec![
+            oxiswap_pool(token_a, token_b),
+            oxiswap_pool(token_b, token_c),
+            AMM::MiraV1(MiraV1 {
+                fee: (1_000, 50, 0, 0),
+                ..Default::default()
+            }),
+        ];
+
+        // 1 - 0.997^2 is 59.91 bps, and 1 - 0.997 * 0.99 is 129.7 bps.
+        assert_eq!(route_total_fee_bps(&[0, 1], &amms), 60);
+        assert_eq!(route_total_fee_bps(&[0, 2], &amms), 130);
+        assert_eq!(route_total_fee_bps(&[1], &amms), 30);
+        assert_eq!(route_total_fee_bps(&[], &amms), 0);
+    }
+
+    #[test]
+    fn test_simulate_swap_with_added_liquidity() {
+        let token_a = AssetId::new([1; 32]);
+        let token_b = AssetId::new([2; 32]);
+        let pool = oxiswap_pool(token_a, token_b);
+        let amount_in = U256::from(10_000);
+
+        let shallow = pool.simulate_swap(token_a, token_b, amount_in).unwrap();
+        let deep = pool
+            .simulate_swap_with_added_liquidity(token_a, amount_in, 1_000_000, 1_000_000)
+            .unwrap();
+        assert!(deep > shallow);
+        assert_eq!(pool.reserves(), (1_000_000, 1_000_000));
+
+        let doubled = AMM::Oxiswap(Oxiswap::new(
+            ContractId::zeroed(),
+            token_a,
+            token_b,
+            2_000_000,
+            2_000_000,
+            300,
+        ));
+        assert_eq!(
+            deep,
+            doubled.simulate_swap(token_a, token_b, amount_in).unwrap()
+        );
+    }
+
+    #[test]
+    fn test_cp_amount_out() {
+        let amount_in = U256::from(1_000_000_000u64);
+        let reserve_in = U256::from(1_000_000_000_000u64);
+        let reserve_out = U256::from(2_000_000_000_000u64);
+
+        for (fee_bps, expected) in [
+            (0, 1_998_001_998u64),
+            (5, 1_997_003_994),
+            (30, 1_992_013_962),
+            (100, 1_978_041_738),
+            (10_000, 0),
+        ] {
+            assert_eq!(
+                cp_amount_out(amount_in, reserve_in, reserve_out, fee_bps).unwrap(),
+                U256::from(expected)
+            );
+        }
+
+        assert!(matches!(
+            cp_amount_out(U256::zero(), U256::zero(), reserve_out, 30),
+            Err(SwapSimulationError::DivisionByZero)
+        ));
+        assert!(matches!(
+            cp_amount_out(U256::MAX, reserve_in, reserve_out, 30),
+            Err(SwapSimulationError::Overflow)
+        ));
+    }
+
+    #[test]
+    fn test_utilization() {
+        let token_a = AssetId::new([1; 32]);
+        let token_b = AssetId::new([2; 32]);
+        assert_eq!(oxiswap_pool(token_a, token_b).utilization(), 0.0);
+
+        let skewed = Oxiswap::new(
+            ContractId::zeroed(),
+            token_a,
+            token_b,
+            1_000,
+            1_000_000_000_000,
+            300,
+        );
+        assert!(skewed.utilization() > 0.999);
+        assert!(skewed.utilization() <= 1.0);
+
+        let empty = Oxiswap::new(ContractId::zeroed(), token_a, token_b, 0, 0, 300);
+        assert_eq!(empty.utilization(), 0.0);
+    }
+
+    #[test]
+    fn test_price_confidence() {
+        let token_a = AssetId::new([1; 32]);
+        let token_b = AssetId::new([2; 32]);
+        // 1_000 whole tokens on each side.
+        let deep = Oxiswap::new(
+            ContractId::zeroed(),
+            token_a,
+            token_b,
+            1_000_000_000_000,
+            1_000_000_000_000,
+            300,
+        );
+
+        let fresh = deep.price_confidence(100, 100, 100, 1_000.0);
+        assert!((fresh - 0.5).abs() < 1e-9);
+        let stale = deep.price_confidence(100, 150, 100, 1_000.0);
+        assert!((stale - 0.25).abs() < 1e-9);
+        assert_eq!(deep.price_confidence(100, 200, 100, 1_000.0), 0.0);
+        assert_eq!(deep.price_confidence(100, 101, 0, 1_000.0), 0.0);
+
+        // A shallower pool at the same age scores lower.
+        let shallow = oxiswap_pool(token_a, token_b);
+        assert!(shallow.price_confidence(100, 150, 100, 1_000.0) < stale);
+
+        let empty = Oxiswap::new(ContractId::zeroed(), token_a, token_b, 0, 0, 300);
+        assert_eq!(empty.price_confidence(100, 100, 100, 1_000.0), 0.0);
+    }
+
+    #[test]
+    fn test_filter_identical_token_pools() {
+        let token_a = AssetId::new([1; 32]);
+        let token_b = AssetId::new([2; 32]);
+        let amms = vec![
+            oxiswap_pool(token_a, token_b),
+            oxiswap_pool(token_a, token_a),
+            AMM::MiraV1(MiraV1::new(
+                ContractId::zeroed(),
+                (token_b, token_b, false),
+                token_b,
+                9,
+                token_b,
+                9,
+                1_000,
+                1_000,
+                (300, 50, 0, 0),
+                false,
+            )),
+        ];
+
+        let filtered = filter_identical_token_pools(amms);
+        assert_eq!(filtered.len(), 1);
+        assert_eq!(filtered[0].tokens(), vec![token_a, token_b]);
+    }
+
+    #[test]
+    fn test_route_geomean_price() {
+        let token_a = AssetId::new([1; 32]);
+        let token_b = AssetId::new([2; 32]);
+        let token_c = AssetId::new([3; 32]);
+        let pool = |token_a, token_b, reserve_b| {
+            AMM::Oxiswap(Oxiswap::new(
+                ContractId::zeroed(),
+                token_a,
+                token_b,
+                1_000_000,
+                reserve_b,
+                300,
+            ))
+        };
+        // A is worth 2 B, and B is worth 8 C.
+        let amms = vec![
+            pool(token_a, token_b, 2_000_000),
+            pool(token_b, token_c, 8_000_000),
+        ];
+
+        let product = amms[0].calculate_price(token_a, token_b).unwrap()
+            * amms[1].calculate_price(token_b, token_c).unwrap();
+        let geomean = route_geomean_price(&[0, 1], &amms).unwrap();
+        assert_eq!(product, 16.0);
+        assert!((geomean - 4.0).abs() < 1e-12);
+        assert!((geomean - product.sqrt()).abs() < 1e-12);
+
+        assert!((route_geomean_price(&[0], &amms).unwrap() - 2.0).abs() < 1e-12);
+        assert_eq!(route_geomean_price(&[], &amms), None);
+        assert_eq!(route_geomean_price(&[0, 2], &amms), None);
+    }
+
+    #[test]
+    fn test_amount_in_for_exact_out() {
+        let token_a = AssetId::new([1; 32]);
+        let token_b = AssetId::new([2; 32]);
+        let amms = [
+            oxiswap_pool(token_a, token_b),
+            AMM::MiraV1(MiraV1::new(
+                ContractId::zeroed(),
+                (token_a, token_b, false),
+                token_a,
+                9,
+                token_b,
+                6,
+                1_000_000_000_000,
+                3_000_000_000,
+                (300, 50, 0, 0),
+                false,
+            )),
+        ];
+
+        for amm in &amms {
+            for target_out in [1u64, 7, 1_000, 123_456] {
+                let target_out = U256::from(target_out);
+                let amount_in = amm.amount_in_for_exact_out(token_b, target_out).unwrap();
+                assert!(amm.simulate_swap(token_a, token_b, amount_in).unwrap() >= target_out);
+                assert!(amm.simulate_swap(token_a, token_b, amount_in - 1).unwrap() < target_out);
+            }
+            assert_eq!(
+                amm.amount_in_for_exact_out(token_b, U256::zero()).unwrap(),
+                U256::zero()
+            );
+        }
+
+        // The whole reserve can never be bought.
+        assert!(matches!(
+            amms[0].amount_in_for_exact_out(token_b, U256::from(1_000_000)),
+            Err(SwapSimulationError::InsufficientLiquidity)
+        ));
+    }
+
+    #[test]
+    fn test_net_output() {
+        let token_a = AssetId::new([1; 32]);
+        let token_b = AssetId::new([2; 32]);
+        let pool = oxiswap_pool(token_a, token_b);
+        let gas = U256::from(5_000);
+
+        // About 996 out for 1_000 in does not clear the gas, about 90_000 for 100_000 in does.
+        assert_eq!(
+            pool.net_output(token_a, U256::from(1_000), gas).unwrap(),
+            U256::zero()
+        );
+        let amount_out = pool
+            .simulate_swap(token_a, token_b, U256::from(100_000))
+            .unwrap();
+        let net = pool.net_output(token_a, U256::from(100_000), gas).unwrap();
+        assert!(!net.is_zero());
+        assert_eq!(net, amount_out - gas);
+    }
+
+    #[test]
+    fn test_round_trip_rate() {
+        let token_a = AssetId::new([1; 32]);
+        let token_b = AssetId::new([2; 32]);
+        let pool = Oxiswap::new(
+            ContractId::zeroed(),
+            token_a,
+            token_b,
+            1_000_000_000_000,
+            1_000_000_000_000,
+            300,
+        );
+
+        // Two 30 bps fees lose about 60 bps, with negligible price impact at this size.
+        let rate = pool
+            .round_trip_rate(token_a, U256::from(1_000_000))
+            .unwrap();
+        assert!(rate < 1.0);
+        assert!((1.0 - rate - 0.006).abs() < 1e-4);
+        assert_eq!(pool.reserves(), (1_000_000_000_000, 1_000_000_000_000));
+
+        assert!(matches!(
+            pool.round_trip_rate(token_a, U256::zero()),
+            Err(SwapSimulationError::DivisionByZero)
+        ));
+    }
+
+    #[test]
+    fn test_liquidity_shares() {
+        let token_a = AssetId::new([1; 32]);
+        let token_b = AssetId::new([2; 32]);
+        let token_c = AssetId::new([3; 32]);
+        let pool = |address, token_b, reserve_a| {
+            AMM::Oxiswap(Oxiswap::new(
+                ContractId::new([address; 32]),
+                token_a,
+                token_b,
+                reserve_a,
+                1_000_000,
+                300,
+            ))
+        };
+        let amms = vec![
+            pool(1, token_b, 1_000_000),
+            pool(2, token_c, 5_000_000),
+            pool(3, token_b, 2_000_000),
+            pool(4, token_b, 1_000_000),
+        ];
+
+        let shares = liquidity_shares(&amms, token_a, token_b);
+        let addresses: Vec<_> = shares.iter().map(|(address, _)| *address).collect();
+        assert_eq!(
+            addresses,
+            vec![
+                ContractId::new([1; 32]),
+                ContractId::new([3; 32]),
+                ContractId::new([4; 32])
+            ]
+        );
+        assert_eq!(shares[0].1, 0.25);
+        assert_eq!(shares[1].1, 0.5);
+        assert!((shares.iter().map(|(_, share)| share).sum::<f64>() - 1.0).abs() < 1e-12);
+
+        assert!(liquidity_shares(&amms, token_b, token_c).is_empty());
+    }
+
+    #[test]
+    fn test_price_at_ratio() {
+        let token_a = AssetId::new([1; 32]);
+        let token_b = AssetId::new([2; 32]);
+        let pool = AMM::MiraV1(MiraV1::new(
+            ContractId::zeroed(),
+            (token_a, token_b, false),
+            token_a,
+            9,
+            token_b,
+            9,
+            1_000_000_000_000,
+            1_200_000_000_000,
+            (300, 50, 0, 0),
+            false,
+        ));
+
+        // Growing the reserve of A against a fixed reserve of B makes A cheaper.
+        let prices: Vec<f64> = [500_000_000_000, 1_000_000_000_000, 2_000_000_000_000]
+            .into_iter()
+            .map(|reserve_0| {
+                pool.price_at_ratio(token_a, reserve_0, 1_200_000_000_000)
+                    .unwrap()
+            })
+            .collect();
+        assert!(prices.windows(2).all(|pair| pair[0] > pair[1]));
+        assert!((prices[1] - 1.2).abs() < 1e-9);
+        assert_eq!(pool.reserves(), (1_000_000_000_000, 1_200_000_000_000));
+
+        assert!(matches!(
+            pool.price_at_ratio(AssetId::new([3; 32]), 1, 1),
+            Err(ArithmeticError::InvalidTokenPair)
+        ));
+    }
+
+    #[test]
+    fn test_would_revert() {
+        let token_a = AssetId::new([1; 32]);
+        let token_b = AssetId::new([2; 32]);
+        let pool = oxiswap_pool(token_a, token_b);
+        let amount_in = U256::from(1_000);
+        let amount_out = quote(&pool, token_a, amount_in).unwrap();
+
+        assert!(!pool.would_revert(token_a, amount_in, amount_out).unwrap());
+        assert!(pool
+            .would_revert(token_a, amount_in, amount_out + 1)
+            .unwrap());
+        assert!(pool
+            .would_revert(token_a, U256::zero(), U256::zero())
+            .unwrap());
+        assert_eq!(pool.reserves(), (1_000_000, 1_000_000));
+
+        let mut paused = MiraV1::new(
+            ContractId::zeroed(),
+            (token_a, token_b, false),
+            token_a,
+            9,
+            token_b,
+            9,
+            1_000_000,
+            1_000_000,
+            (300, 50, 0, 0),
+            false,
+        );
+        paused.is_paused = true;
+        assert!(AMM::MiraV1(paused)
+            .would_revert(token_a, amount_in, U256::zero())
+            .unwrap());
+
+        assert!(matches!(
+            pool.would_revert(token_a, U256::MAX, U256::zero()),
+            Err(SwapSimulationError::Overflow)
+        ));
+    }
+
+    #[test]
+    fn test_validate_state() {
+        let token_a = AssetId::new([1; 32]);
+        let token_b = AssetId::new([2; 32]);
+        assert!(oxiswap_pool(token_a, token_b).validate_state().is_ok());
+
+        assert!(matches!(
+            oxiswap_pool(token_a, token_a).validate_state(),
+            Err(AMMError::InvalidState(_))
+        ));
+        let mut pool = MiraV1::new(
+            ContractId::zeroed(),
+            (token_a, token_b, false),
+            token_a,
+            19,
+            token_b,
+            9,
+            1_000_000,
+            1_000_000,
+            (300, 50, 0, 0),
+            false,
+        );
+        assert!(matches!(
+            AMM::MiraV1(pool).validate_state(),
+            Err(AMMError::InvalidState(_))
+        ));
+        pool.token_0_decimals = 9;
+        pool.fee = (100_000, 50, 0, 0);
+        assert!(matches!(
+            AMM::MiraV1(pool).validate_state(),
+            Err(AMMError::InvalidState(_))
+        ));
+    }
+
+    #[test]
+    fn test_price_after_donation() {
+        let token_a = AssetId::new([1; 32]);
+        let token_b = AssetId::new([2; 32]);
+        let pool = AMM::MiraV1(MiraV1::new(
+            ContractId::zeroed(),
+            (token_a, token_b, false),
+            token_a,
+            9,
+            token_b,
+            9,
+            1_000_000_000_000,
+            1_200_000_000_000,
+            (300, 50, 0, 0),
+            false,
+        ));
+
+        // A donation makes the donated token cheaper, from 1.2 to 1 B per A and from 1 / 1.2 to
+        // 1 / 1.5 A per B.
+        let price_a = pool.price_after_donation(token_a, 200_000_000_000).unwrap();
+        let price_b = pool.price_after_donation(token_b, 300_000_000_000).unwrap();
+        assert!((price_a - 1.0).abs() < 1e-9);
+        assert!((price_b - 1.0 / 1.5).abs() < 1e-9);
+        assert_eq!(pool.reserves(), (1_000_000_000_000, 1_200_000_000_000));
+
+        assert!(matches!(
+            pool.price_after_donation(AssetId::new([3; 32]), 1),
+            Err(ArithmeticError::InvalidTokenPair)
+        ));
+    }
+
+    #[test]
+    fn test_waterfill_split() {
+        let token_a = AssetId::new([1; 32]);
+        let token_b = AssetId::new([2; 32]);
+        let oxiswap = |reserve| {
+            AMM::Oxiswap(Oxiswap::new(
+                ContractId::zeroed(),
+                token_a,
+                token_b,
+                reserve,
+                reserve,
+                300,
+            ))
+        };
+        let pools = [
+            oxiswap(1_000_000_000),
+            oxiswap(4_000_000_000),
+            AMM::MiraV1(MiraV1::new(
+                ContractId::zeroed(),
+                (token_a, token_b, false),
+                token_a,
+                9,
+                token_b,
+                9,
+                2_000_000_000,
+                2_000_000_000,
+                (300, 50, 0, 0),
+                false,
+            )),
+        ];
+        let pools: Vec<&AMM> = pools.iter().collect();
+        let amount_in = U256::from(1_000_000_001);
+
+        let allocation = waterfill_split(&pools, token_a, amount_in, 100);
+        assert_eq!(
+            allocation
+                .iter()
+                .fold(U256::zero(), |sum, amount| sum + *amount),
+            amount_in
+        );
+        assert!(allocation[1] > allocation[2] && allocation[2] > allocation[0]);
+
+        let split_out = pools
+            .iter()
+            .zip(&allocation)
+            .map(|(pool, amount)| pool.simulate_swap(token_a, token_b, *amount).unwrap())
+            .fold(U256::zero(), |sum, out| sum + out);
+        for pool in &pools {
+            assert!(split_out > pool.simulate_swap(token_a, token_b, amount_in).unwrap());
+        }
+
+        assert!(waterfill_split(&pools, token_a, amount_in, 0)
+            .iter()
+            .all(|amount| amount.is_zero()));
+
+        // Chunks that no pool can quote still go to a pool, so none are dropped.
+        let mut paused = MiraV1::new(
+            ContractId::zeroed(),
+            (token_a, token_b, false),
+            token_a,
+            9,
+            token_b,
+            9,
+            2_000_000_000,
+            2_000_000_000,
+            (300, 50, 0, 0),
+            false,
+        );
+        paused.is_paused = true;
+        let paused = [AMM::MiraV1(paused.clone()), AMM::MiraV1(paused)];
+        let paused: Vec<&AMM> = paused.iter().collect();
+        assert_eq!(
+            waterfill_split(&paused, token_a, amount_in, 100),
+            vec![amount_in, U256::zero()]
+        );
+    }
+
+    #[test]
+    fn test_state_fingerprint() {
+        let token_a = AssetId::new([1; 32]);
+        let token_b = AssetId::new([2; 32]);
+        let pool = Oxiswap::new(
+            ContractId::zeroed(),
+            token_a,
+            token_b,
+            1_000_000,
+            1_000_000,
+            300,
+        );
+        let same = Oxiswap {
+            address: ContractId::new([9; 32]),
+            ..pool
+        };
+        assert_eq!(pool.state_fingerprint(), same.state_fingerprint());
+
+        let mut swapped = pool;
+        swapped
+            .simulate_swap_mut(token_a, token_b, U256::from(1_000))
+            .unwrap();
+        assert_ne!(pool.state_fingerprint(), swapped.state_fingerprint());
+
+        let fee_changed = Oxiswap { fee: 100, ..pool };
+        assert_ne!(pool.state_fingerprint(), fee_changed.state_fingerprint());
+    }
+
+    #[test]
+    fn test_reserve_snapshot_round_trip() {
+        let token_a = AssetId::new([1; 32]);
+        let token_b = AssetId::new([2; 32]);
+        let pool = |reserve_0, reserve_1| {
+            AMM::MiraV1(MiraV1 {
+                pool_id: (token_a, token_b, false),
+                reserve_0,
+                reserve_1,
+                ..Default::default()
+            })
+        };
+        let latest = pool(1_500, 2_500);
+        let mut cached = pool(1_000, 3_000);
+
+        let snap = latest.reserve_snapshot();
+        assert_eq!(snap.pool_key(), latest.pool_key());
+        cached.apply_reserve_snapshot(&snap);
+        assert_eq!(cached.reserves(), (1_500, 2_500));
+
+        let mut other = oxiswap_pool(token_a, token_b);
+        other.apply_reserve_snapshot(&snap);
+        assert_eq!(other.reserves(), (1_000_000, 1_000_000));
+    }
+
+    #[test]
+    fn test_has_liquidity() {
+        let pool = |reserve_0, reserve_1| {
+            AMM::MiraV1(MiraV1 {
+                reserve_0,
+                reserve_1,
+                ..Default::default()
+            })
+        };
+        let amms = vec![
+            pool(0, 0),
+            pool(0, 1_000),
+            pool(1_000, 0),
+            pool(1_000, 1_000),
+        ];
+
+        assert!(!amms[0].has_liquidity());
+        assert!(!amms[1].has_liquidity());
+        assert!(!amms[2].has_liquidity());
+        assert!(amms[3].has_liquidity());
+
+        let filtered = filter_amms_with_empty_reserves(amms);
+        assert_eq!(filtered.len(), 1);
+        assert_eq!(filtered[0].reserves(), (1_000, 1_000));
+    }
+
+    #[test]
+    fn test_clone_boxed_amm() {
+        let token_a = AssetId::new([1; 32]);
+        let token_b = AssetId::new([2; 32]);
+        let boxed: Box<dyn AutomatedMarketMaker> = Box::new(MiraV1 {
+            address: ContractId::new([9; 32]),
+            pool_id: (token_a, token_b, false),
+            token_0: token_a,
+            token_1: token_b,
+            reserve_0: 1_000_000,
+            reserve_1: 2_000_000,
+            fee: (300, 50, 0, 0),
+            ..Default::default()
+        });
+        let mut cloned = boxed.clone();
+
+        assert_eq!(cloned.address(), boxed.address());
+        assert_eq!(cloned.tokens(), boxed.tokens());
+        let amount_in = U256::from(1_000);
+        let out = boxed.simulate_swap(token_a, token_b, amount_in).unwrap();
+        assert_eq!(
+            cloned.simulate_swap(token_a, token_b, amount_in).unwrap(),
+            out
+        );
+
+        // The clone is independent of the original.
+        cloned
+            .simulate_swap_mut(token_a, token_b, amount_in)
+            .unwrap();
+        assert_eq!(
+            boxed.simulate_swap(token_a, token_b, amount_in).unwrap(),
+            out
+        );
+    }
+
+    #[test]
+    fn test_bid_ask() {
+        let token_a = AssetId::new([1; 32]);
+        let token_b = AssetId::new([2; 32]);
+        let amm = oxiswap_pool(token_a, token_b);
+
+        let (bid, ask) = amm.bid_ask(token_a, U256::from(1_000)).unwrap();
+        let mid = amm.calculate_price(token_a, token_b).unwrap();
+        assert!(bid < mid && mid < ask);
+        assert!((bid - 0.996).abs() < 1e-12);
+
+        assert!(amm.bid_ask(token_a, U256::zero()).is_err());
+
+        // 1_000 of A at 6 decimals against 1_200 of B at 9 decimals, trading one whole A.
+        let amm = AMM::MiraV1(MiraV1::new(
+            ContractId::zeroed(),
+            (token_a, token_b, false),
+            token_a,
+            6,
+            token_b,
+            9,
+            1_000_000_000,
+            1_200_000_000_000,
+            (300, 50, 0, 0),
+            false,
+        ));
+        let (bid, ask) = amm.bid_ask(token_a, U256::from(1_000_000)).unwrap();
+        let mid = amm.calculate_price(token_a, token_b).unwrap();
+        assert!((mid - 1.2).abs() < 1e-9);
+        assert!(bid < mid && mid < ask);
+        assert!((bid - 1.195208377).abs() < 1e-9);
+        assert!((ask - 1.204815649).abs() < 1e-9);
+    }
+
+    #[test]
+    fn test_verify_quote() {
+        let token_a = AssetId::new([1; 32]);
+        let token_b = AssetId::new([2; 32]);
+        let amm = oxiswap_pool(token_a, token_b);
+        let amount_in = U256::from(1_000);
+
+        assert!(amm
+            .verify_quote(token_a, amount_in, U256::from(996))
+            .unwrap());
+        assert!(amm
+            .verify_quote(token_a, amount_in, U256::from(990))
+            .unwrap());
+        assert!(!amm
+            .verify_quote(token_a, amount_in, U256::from(997))
+            .unwrap());
+    }
+
+    #[test]
+    fn test_supports_swap() {
+        let token_a = AssetId::new([1; 32]);
+        let token_b = AssetId::new([2; 32]);
+        let token_c = AssetId::new([3; 32]);
+        let mira = AMM::MiraV1(MiraV1 {
+            token_0: token_a,
+            token_1: token_b,
+            ..Default::default()
+        });
+        let oxiswap = oxiswap_pool(token_a, token_b);
+
+        for amm in [mira, oxiswap] {
+            assert!(amm.supports_swap(token_a, token_b));
+            assert!(amm.supports_swap(token_b, token_a));
+            assert!(!amm.supports_swap(token_a, token_c));
+            assert!(!amm.supports_swap(token_a, token_a));
+        }
+    }
+
+    #[test]
+    fn test_custom_amm() {
+        let token_a = AssetId::new([1; 32]);
+        let token_b = AssetId::new([2; 32]);
+        let peg = |reserve_b| {
+            AMM::Custom(Box::new(PegPool {
+                token_a,
+                token_b,
+                reserve_a: 1_000,
+                reserve_b,
+            }))
+        };
+        let amms = vec![peg(0), oxiswap_pool(token_a, token_b), peg(1_000)];
+
+        let mut amms = filter_amms_with_empty_reserves(amms);
+        assert_eq!(amms.len(), 2);
+        assert_eq!(amms[1].address(), ContractId::new([7; 32]));
+        assert!(amms[1].supports_swap(token_a, token_b));
+        assert_eq!(
+            quote(&amms[1], token_a, U256::from(400)).unwrap(),
+            U256::from(400)
+        );
+
+        let cloned = amms[1].clone();
+        amms[1]
+            .simulate_swap_mut(token_a, token_b, U256::from(400))
+            .unwrap();
+        assert_eq!(amms[1].reserves(), (1_400, 600));
+        assert_eq!(cloned.reserves(), (1_000, 1_000));
+
+        assert!(serde_json::to_string(&cloned).is_err());
+    }
 }
@@ -5,18 +5,33 @@ use fuels::{
 };
 use serde::{Deserialize, Serialize};
 
-use super::AutomatedMarketMaker;
+use super::{
+    consts::FUEL_DEFAULT_DECIMALS, fee::Bps, mira::u256_to_f64, reduce_ratio, AutomatedMarketMaker,
+    RawState, SimConfig, AMM,
+};
 use crate::errors::{AMMError, ArithmeticError, SwapSimulationError};
 
 /// Represents an Oxiswap pool.
-#[derive(Debug, Clone, Copy, Default, Serialize, Deserialize)]
+///
+/// Reserves are raw token amounts, as held by the pool contract.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
 pub struct Oxiswap {
     pub address: ContractId,
     pub token_a: AssetId,
     pub token_b: AssetId,
     pub reserve_a: u64,
     pub reserve_b: u64,
+    // Raw fee in tenths of a basis point, see `fee_bps`
     pub fee: u64,
+    #[serde(default = "default_decimals")]
+    pub token_a_decimals: u8,
+    #[serde(default = "default_decimals")]
+    pub token_b_decimals: u8,
+}
+
+// Pools serialized before the decimals were tracked assumed native Fuel decimals.
+fn default_decimals() -> u8 {
+    FUEL_DEFAULT_DECIMALS
 }
 
 #[async_trait]
@@ -27,7 +42,7 @@ impl AutomatedMarketMaker for Oxiswap {
 
     /// Synchronizes the AMM's state with the blockchain.
     async fn sync(&mut self, wallet: Wallet) -> Result<(), AMMError> {
-        let (reserve_a, reserve_b) = self.get_reserves(wallet).await?;
+        let (reserve_a, reserve_b) = self.get_reserves(wallet, &SimConfig::default()).await?;
         self.reserve_a = reserve_a;
         self.reserve_b = reserve_b;
         Ok(())
@@ -38,19 +53,35 @@ impl AutomatedMarketMaker for Oxiswap {
     }
 
     /// Calculates the price of the base token in terms of the other token.
+    ///
+    /// The price is per whole token, so it accounts for the token decimals.
     fn calculate_price(
         &self,
         base_token: AssetId,
         _quote_token: AssetId,
     ) -> Result<f64, ArithmeticError> {
-        let (reserve_in, reserve_out) = if base_token == self.token_a {
-            (self.reserve_a, self.reserve_b)
+        let (reserve_in, reserve_out, decimals_in, decimals_out) = if base_token == self.token_a {
+            (
+                self.reserve_a,
+                self.reserve_b,
+                self.token_a_decimals,
+                self.token_b_decimals,
+            )
         } else {
-            (self.reserve_b, self.reserve_a)
+            (
+                self.reserve_b,
+                self.reserve_a,
+                self.token_b_decimals,
+                self.token_a_decimals,
+            )
         };
+        if reserve_in == 0 {
+            return Err(ArithmeticError::DivisionByZero);
+        }
 
-        let some_price = reserve_out.checked_div(reserve_in);
-        Ok(some_price.unwrap() as f64)
+        let value_out = U256::from(reserve_out) * U256::from(10).pow(U256::from(decimals_in));
+        let value_in = U256::from(reserve_in) * U256::from(10).pow(U256::from(decimals_out));
+        Ok(u256_to_f64(value_out) / u256_to_f64(value_in))
     }
 
     /// Populates the AMM's data from the blockchain.
@@ -59,7 +90,7 @@ impl AutomatedMarketMaker for Oxiswap {
         _block_number: Option<u64>,
         wallet: Wallet,
     ) -> Result<(), AMMError> {
-        *self = self.get_pool_info(wallet).await?;
+        *self = self.get_pool_info(wallet, &SimConfig::default()).await?;
         Ok(())
     }
 
@@ -71,17 +102,17 @@ impl AutomatedMarketMaker for Oxiswap {
         amount_in: U256,
     ) -> Result<U256, SwapSimulationError> {
         if self.token_a == base_token {
-            Ok(self.get_amount_out(
+            self.checked_amount_out(
                 amount_in,
                 U256::from(self.reserve_a),
                 U256::from(self.reserve_b),
-            ))
+            )
         } else {
-            Ok(self.get_amount_out(
+            self.checked_amount_out(
                 amount_in,
                 U256::from(self.reserve_b),
                 U256::from(self.reserve_a),
-            ))
+            )
         }
     }
 
@@ -92,29 +123,41 @@ impl AutomatedMarketMaker for Oxiswap {
         _quote_token: AssetId,
         amount_in: U256,
     ) -> Result<U256, SwapSimulationError> {
-        if self.token_a == base_token {
-            let amount_out = self.get_amount_out(
+        #[cfg(debug_assertions)]
+        let k_before = U256::from(self.reserve_a) * U256::from(self.reserve_b);
+
+        let amount_out = if self.token_a == base_token {
+            let amount_out = self.checked_amount_out(
                 amount_in,
                 U256::from(self.reserve_a),
                 U256::from(self.reserve_b),
-            );
+            )?;
 
             self.reserve_a += amount_in.as_u64();
             self.reserve_b -= amount_out.as_u64();
 
-            Ok(amount_out)
+            amount_out
         } else {
-            let amount_out = self.get_amount_out(
+            let amount_out = self.checked_amount_out(
                 amount_in,
                 U256::from(self.reserve_b),
                 U256::from(self.reserve_a),
-            );
+            )?;
 
             self.reserve_a -= amount_out.as_u64();
             self.reserve_b += amount_in.as_u64();
 
-            Ok(amount_out)
-        }
+            amount_out
+        };
+
+        // Fees stay in the pool, so a valid swap can never decrease the invariant.
+        #[cfg(debug_assertions)]
+        debug_assert!(
+            U256::from(self.reserve_a) * U256::from(self.reserve_b) >= k_before,
+            "simulate_swap_mut decreased the pool invariant"
+        );
+
+        Ok(amount_out)
     }
 
     fn get_token_out(&self, token_in: AssetId) -> AssetId {
@@ -124,10 +167,48 @@ impl AutomatedMarketMaker for Oxiswap {
             self.token_a
         }
     }
+
+    fn supports_swap(&self, token_in: AssetId, token_out: AssetId) -> bool {
+        (token_in == self.token_a && token_out == self.token_b)
+            || (token_in == self.token_b && token_out == self.token_a)
+    }
+
+    fn reserves(&self) -> (u64, u64) {
+        (self.reserve_a, self.reserve_b)
+    }
+
+    fn normalized_reserves(&self) -> (U256, U256) {
+        (
+            to_18_decimals(self.reserve_a, self.token_a_decimals),
+            to_18_decimals(self.reserve_b, self.token_b_decimals),
+        )
+    }
+
+    fn raw_state(&self) -> RawState {
+        RawState {
+            reserve_0: U256::from(self.reserve_a),
+            reserve_1: U256::from(self.reserve_b),
+            decimals_0: self.token_a_decimals,
+            decimals_1: self.token_b_decimals,
+            fee_bps: self.fee_bps().as_bps(),
+            is_stable: false,
+        }
+    }
+
+    fn has_liquidity(&self) -> bool {
+        self.reserve_a != 0 && self.reserve_b != 0
+    }
+
+    fn lp_asset_id(&self) -> Option<AssetId> {
+        None
+    }
 }
 
 impl Oxiswap {
     /// Creates a new Oxiswap instance.
+    ///
+    /// The tokens are stored in canonical byte order, see [`Oxiswap::normalize`]. Both tokens are
+    /// assumed to use `FUEL_DEFAULT_DECIMALS` until set with [`Oxiswap::with_decimals`].
     pub fn new(
         address: ContractId,
         token_a: AssetId,
@@ -136,36 +217,331 @@ impl Oxiswap {
         reserve_b: u64,
         fee: u64,
     ) -> Self {
-        Self {
+        let mut pool = Self {
             address,
             token_a,
             token_b,
             reserve_a,
             reserve_b,
             fee,
+            token_a_decimals: FUEL_DEFAULT_DECIMALS,
+            token_b_decimals: FUEL_DEFAULT_DECIMALS,
+        };
+        pool.normalize();
+        pool
+    }
+
+    /// Sets the decimals of `token_a` and `token_b`, in the pool's canonical token order.
+    pub fn with_decimals(mut self, token_a_decimals: u8, token_b_decimals: u8) -> Self {
+        self.token_a_decimals = token_a_decimals;
+        self.token_b_decimals = token_b_decimals;
+        self
+    }
+
+    /// Orders `token_a`/`token_b` by their bytes, swapping the reserves accordingly.
+    ///
+    /// This keeps two instances of the same pool identical regardless of argument order.
+    pub fn normalize(&mut self) {
+        if self.token_a > self.token_b {
+            std::mem::swap(&mut self.token_a, &mut self.token_b);
+            std::mem::swap(&mut self.reserve_a, &mut self.reserve_b);
+            std::mem::swap(&mut self.token_a_decimals, &mut self.token_b_decimals);
         }
     }
 
+    /// Returns the swap fee in basis points.
+    pub fn fee_bps(&self) -> Bps {
+        Bps::from_raw(self.fee)
+    }
+
+    /// Returns the reserves, to be handed back to [`Oxiswap::restore_reserves`].
+    pub fn reserve_checkpoint(&self) -> (u64, u64) {
+        (self.reserve_a, self.reserve_b)
+    }
+
+    /// Rolls the reserves back to a checkpoint from [`Oxiswap::reserve_checkpoint`].
+    pub fn restore_reserves(&mut self, cp: (u64, u64)) {
+        (self.reserve_a, self.reserve_b) = cp;
+    }
+
+    /// Returns `reserve_a : reserve_b` reduced to lowest terms.
+    pub fn reserve_ratio_reduced(&self) -> (u64, u64) {
+        reduce_ratio(self.reserve_a, self.reserve_b)
+    }
+
     /// Fetches the current pool information from the blockchain.
-    pub async fn get_pool_info(&self, wallet: Wallet) -> Result<Oxiswap, AMMError> {
+    ///
+    /// Not implemented yet: the crate has no Oxiswap contract bindings to read the pool with.
+    pub async fn get_pool_info(
+        &self,
+        wallet: Wallet,
+        sim_config: &SimConfig,
+    ) -> Result<Oxiswap, AMMError> {
         todo!()
     }
 
     /// Fetches the current reserves from the blockchain.
-    pub async fn get_reserves(&self, wallet: Wallet) -> Result<(u64, u64), AMMError> {
+    pub async fn get_reserves(
+        &self,
+        wallet: Wallet,
+        sim_config: &SimConfig,
+    ) -> Result<(u64, u64), AMMError> {
         todo!()
     }
 
     /// Calculates the amount received for a given `amount_in` `reserve_in` and `reserve_out`.
+    ///
+    /// All amounts are raw. The constant product invariant holds on raw amounts, so unlike Mira's
+    /// stable curve the output does not depend on the token decimals.
     pub fn get_amount_out(&self, amount_in: U256, reserve_in: U256, reserve_out: U256) -> U256 {
         if amount_in.is_zero() || reserve_in.is_zero() || reserve_out.is_zero() {
             return U256::zero();
         }
-        let fee = (10000 - (self.fee / 10)) / 10; //Fee of 300 => (10,000 - 30) / 10  = 997
-        let amount_in_with_fee = amount_in * U256::from(fee);
+        let amount_in_with_fee = amount_in * U256::from(self.fee_bps().complement_raw());
         let numerator = amount_in_with_fee * reserve_out;
-        let denominator = reserve_in * U256::from(1000) + amount_in_with_fee;
+        let denominator = reserve_in * U256::from(Bps::RAW_DENOMINATOR) + amount_in_with_fee;
 
         numerator / denominator
     }
+
+    /// Calculates the amount out like `get_amount_out`, checking that it leaves part of
+    /// `reserve_out` in the pool.
+    ///
+    /// No valid swap can drain the out reserve, so a larger output is reported as
+    /// `InsufficientLiquidity`.
+    pub fn checked_amount_out(
+        &self,
+        amount_in: U256,
+        reserve_in: U256,
+        reserve_out: U256,
+    ) -> Result<U256, SwapSimulationError> {
+        let amount_out = self.get_amount_out(amount_in, reserve_in, reserve_out);
+        if !amount_out.is_zero() && amount_out >= reserve_out {
+            return Err(SwapSimulationError::InsufficientLiquidity);
+        }
+
+        Ok(amount_out)
+    }
+}
+
+/// Scales a raw amount with `decimals` to 18 decimals.
+fn to_18_decimals(amount: u64, decimals: u8) -> U256 {
+    if decimals <= 18 {
+        U256::from(amount) * U256::from(10).pow(U256::from(18 - decimals))
+    } else {
+        U256::from(amount) / U256::from(10).pow(U256::from(decimals - 18))
+    }
+}
+
+/// Builds an Oxiswap pool and wraps it in an [`AMM`], storing the tokens in canonical order.
+pub fn oxiswap_amm(
+    address: ContractId,
+    token_a: AssetId,
+    token_b: AssetId,
+    reserve_a: u64,
+    reserve_b: u64,
+    fee: u64,
+) -> AMM {
+    AMM::Oxiswap(Oxiswap::new(
+        address, token_a, token_b, reserve_a, reserve_b, fee,
+    ))
+}
+
+/// Merges two Oxiswap pools of the same pair into one synthetic constant product pool.
+///
+/// The reserves are summed and the fee is weighted by each pool's `reserve_a`. Quotes from the
+/// merged pool only approximate routing across both pools. Returns `None` if the pools trade
+/// different pairs or the summed reserves overflow.
+pub fn synthetic_merged_pool(a: &Oxiswap, b: &Oxiswap) -> Option<Oxiswap> {
+    let (mut a, mut b) = (*a, *b);
+    a.normalize();
+    b.normalize();
+    if (a.token_a, a.token_b) != (b.token_a, b.token_b) {
+        return None;
+    }
+
+    let reserve_a = a.reserve_a.checked_add(b.reserve_a)?;
+    let reserve_b = a.reserve_b.checked_add(b.reserve_b)?;
+    let fee = if reserve_a == 0 {
+        a.fee.max(b.fee)
+    } else {
+        ((a.fee as u128 * a.reserve_a as u128 + b.fee as u128 * b.reserve_a as u128)
+            / reserve_a as u128) as u64
+    };
+
+    Some(Oxiswap {
+        address: ContractId::zeroed(),
+        token_a: a.token_a,
+        token_b: a.token_b,
+        reserve_a,
+        reserve_b,
+        fee,
+        token_a_decimals: a.token_a_decimals,
+        token_b_decimals: a.token_b_decimals,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use fuels::types::{AssetId, ContractId, U256};
+
+    use super::{oxiswap_amm, synthetic_merged_pool, Oxiswap};
+    use crate::{
+        amm::{AutomatedMarketMaker, AMM},
+        errors::ArithmeticError,
+    };
+
+    #[test]
+    fn test_new_normalizes_token_order() {
+        let token_a = AssetId::new([1; 32]);
+        let token_b = AssetId::new([2; 32]);
+        let pool = Oxiswap::new(ContractId::zeroed(), token_a, token_b, 100, 200, 300);
+        let swapped = Oxiswap::new(ContractId::zeroed(), token_b, token_a, 200, 100, 300);
+
+        assert_eq!(pool, swapped);
+        assert_eq!(swapped.get_token_out(token_b), token_a);
+        assert_eq!(
+            pool.simulate_swap(token_b, token_a, U256::from(10))
+                .unwrap(),
+            swapped
+                .simulate_swap(token_b, token_a, U256::from(10))
+                .unwrap()
+        );
+    }
+
+    #[test]
+    fn test_calculate_price_zero_reserve() {
+        let token_a = AssetId::new([1; 32]);
+        let token_b = AssetId::new([2; 32]);
+        let pool = Oxiswap::new(ContractId::zeroed(), token_a, token_b, 0, 1_000, 300);
+
+        assert!(matches!(
+            pool.calculate_price(token_a, token_b),
+            Err(ArithmeticError::DivisionByZero)
+        ));
+        assert_eq!(pool.calculate_price(token_b, token_a).unwrap(), 0.0);
+    }
+
+    #[test]
+    fn test_calculate_price_per_whole_token() {
+        let token_a = AssetId::new([1; 32]);
+        let token_b = AssetId::new([2; 32]);
+        // 1_000 whole A at 6 decimals against 2_000 whole B at 9 decimals.
+        let pool = Oxiswap::new(
+            ContractId::zeroed(),
+            token_a,
+            token_b,
+            1_000_000_000,
+            2_000_000_000_000,
+            300,
+        )
+        .with_decimals(6, 9);
+
+        assert_eq!(pool.calculate_price(token_a, token_b).unwrap(), 2.0);
+        assert_eq!(pool.calculate_price(token_b, token_a).unwrap(), 0.5);
+
+        let (numerator, denominator) = AMM::Oxiswap(pool).spot_price_ratio(token_a).unwrap();
+        assert_eq!(numerator, denominator * 2);
+    }
+
+    #[test]
+    fn test_restore_reserves() {
+        let token_a = AssetId::new([1; 32]);
+        let token_b = AssetId::new([2; 32]);
+        let original = Oxiswap::new(ContractId::zeroed(), token_a, token_b, 1_000, 2_000, 300);
+        let mut pool = original;
+        let checkpoint = pool.reserve_checkpoint();
+
+        pool.simulate_swap_mut(token_a, token_b, U256::from(100))
+            .unwrap();
+        assert_ne!(pool, original);
+
+        pool.restore_reserves(checkpoint);
+        assert_eq!(pool, original);
+    }
+
+    #[test]
+    fn test_synthetic_merged_pool() {
+        let token_a = AssetId::new([1; 32]);
+        let token_b = AssetId::new([2; 32]);
+        let token_c = AssetId::new([3; 32]);
+        let first = Oxiswap::new(
+            ContractId::zeroed(),
+            token_a,
+            token_b,
+            1_000_000,
+            2_000_000,
+            300,
+        );
+        let second = Oxiswap {
+            address: ContractId::new([9; 32]),
+            token_a: token_b,
+            token_b: token_a,
+            reserve_a: 6_000_000,
+            reserve_b: 3_000_000,
+            fee: 100,
+            ..first
+        };
+
+        let merged = synthetic_merged_pool(&first, &second).unwrap();
+        assert_eq!((merged.token_a, merged.token_b), (token_a, token_b));
+        assert_eq!((merged.reserve_a, merged.reserve_b), (4_000_000, 8_000_000));
+        assert_eq!(merged.fee, 150);
+
+        let amount_in = U256::from(10_000);
+        let merged_out = merged.simulate_swap(token_a, token_b, amount_in).unwrap();
+        assert!(merged_out > first.simulate_swap(token_a, token_b, amount_in).unwrap());
+        assert!(merged_out > second.simulate_swap(token_a, token_b, amount_in).unwrap());
+        assert!(merged_out < amount_in * U256::from(2));
+
+        let other = Oxiswap::new(ContractId::zeroed(), token_a, token_c, 1_000, 1_000, 300);
+        assert!(synthetic_merged_pool(&first, &other).is_none());
+    }
+
+    #[test]
+    fn test_oxiswap_amm() {
+        let token_a = AssetId::new([1; 32]);
+        let token_b = AssetId::new([2; 32]);
+        let amm = oxiswap_amm(ContractId::zeroed(), token_b, token_a, 200, 100, 300);
+
+        let AMM::Oxiswap(pool) = amm else {
+            panic!("expected an Oxiswap pool");
+        };
+        assert_eq!((pool.token_a, pool.token_b), (token_a, token_b));
+        assert_eq!((pool.reserve_a, pool.reserve_b), (100, 200));
+    }
+
+    #[test]
+    fn test_asymmetric_decimals() {
+        let usdc = AssetId::new([1; 32]);
+        let eth = AssetId::new([2; 32]);
+        // 10,000 USDC with 6 decimals against 5 ETH with 18 decimals.
+        let pool = Oxiswap::new(
+            ContractId::zeroed(),
+            eth,
+            usdc,
+            5_000_000_000_000_000_000,
+            10_000_000_000,
+            300,
+        )
+        .with_decimals(6, 18);
+
+        assert_eq!((pool.token_a, pool.token_a_decimals), (usdc, 6));
+        assert_eq!(
+            pool.normalized_reserves(),
+            (
+                U256::from(10_000_000_000_000_000_000_000u128),
+                U256::from(5_000_000_000_000_000_000u64)
+            )
+        );
+
+        assert_eq!(pool.reserves_human(), (10_000.0, 5.0));
+
+        // The pool swaps raw amounts: 100 USDC buys about 0.0494 ETH after the 30 bps fee.
+        assert_eq!(
+            pool.simulate_swap(usdc, eth, U256::from(100_000_000))
+                .unwrap(),
+            U256::from(49_357_901_719_853_064u64)
+        );
+    }
 }
@@ -0,0 +1,499 @@
+use std::{cmp::Ordering, collections::VecDeque};
+
+use fuels::types::{AssetId, U256};
+use serde::{Deserialize, Serialize};
+
+use super::{mira::u256_to_f64, q64::Q64, AutomatedMarketMaker, AMM};
+use crate::errors::{ArithmeticError, SwapSimulationError};
+
+/// Exponentially weighted moving average of a pool's spot price.
+#[derive(Debug, Clone, Copy, Serialize, Deserialize)]
+pub struct EwmaPrice {
+    pub alpha: f64,
+    pub value: Option<f64>,
+}
+
+impl EwmaPrice {
+    /// Creates a new EWMA with a smoothing factor `alpha` in `(0, 1]`.
+    pub fn new(alpha: f64) -> Result<Self, ArithmeticError> {
+        if alpha.is_nan() || alpha <= 0.0 || alpha > 1.0 {
+            return Err(ArithmeticError::InvalidSmoothingFactor);
+        }
+
+        Ok(Self { alpha, value: None })
+    }
+
+    /// Folds the current price of `base` in `amm` into the average.
+    ///
+    /// Returns the smoothed price.
+    pub fn update(&mut self, amm: &AMM, base: AssetId) -> Result<f64, ArithmeticError> {
+        let price = amm.calculate_price(base, amm.get_token_out(base))?;
+        Ok(self.update_price(price))
+    }
+
+    /// Folds `price` into the average.
+    ///
+    /// Returns the smoothed price.
+    pub fn update_price(&mut self, price: f64) -> f64 {
+        let value = match self.value {
+            Some(value) => self.alpha * price + (1.0 - self.alpha) * value,
+            None => price,
+        };
+        self.value = Some(value);
+        value
+    }
+}
+
+/// Bounded history of `(block, reserve_0, reserve_1)` samples for a single pool.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ReserveHistory {
+    pub capacity: usize,
+    pub samples: VecDeque<(u64, u64, u64)>,
+}
+
+impl ReserveHistory {
+    pub fn new(capacity: usize) -> Self {
+        Self {
+            capacity,
+            samples: VecDeque::with_capacity(capacity),
+        }
+    }
+
+    /// Records the reserves at `block`, evicting the oldest sample once the history is full.
+    pub fn push(&mut self, block: u64, reserve_0: u64, reserve_1: u64) {
+        if self.capacity == 0 {
+            return;
+        }
+        if self.samples.len() == self.capacity {
+            self.samples.pop_front();
+        }
+        self.samples.push_back((block, reserve_0, reserve_1));
+    }
+
+    /// Calculates the sample standard deviation of the log returns of `reserve_1 / reserve_0`.
+    ///
+    /// Samples with an empty reserve are skipped. Returns zero with fewer than two returns.
+    pub fn realized_volatility(&self) -> f64 {
+        let prices: Vec<f64> = self
+            .samples
+            .iter()
+            .filter(|(_, reserve_0, reserve_1)| *reserve_0 != 0 && *reserve_1 != 0)
+            .map(|(_, reserve_0, reserve_1)| *reserve_1 as f64 / *reserve_0 as f64)
+            .collect();
+        let returns: Vec<f64> = prices.windows(2).map(|w| (w[1] / w[0]).ln()).collect();
+        if returns.len() < 2 {
+            return 0.0;
+        }
+
+        let n = returns.len() as f64;
+        let mean = returns.iter().sum::<f64>() / n;
+        let variance = returns.iter().map(|r| (r - mean).powi(2)).sum::<f64>() / (n - 1.0);
+        variance.sqrt()
+    }
+}
+
+/// Calculates the price of `base_per_pool[i]` in `amms[i]` for each pool.
+///
+/// Volatile Mira pools skip the `BigFloat` conversion used by `calculate_price`. Extra entries in
+/// the longer slice are ignored.
+pub fn calculate_prices_batch(
+    amms: &[AMM],
+    base_per_pool: &[AssetId],
+) -> Vec<Result<f64, ArithmeticError>> {
+    amms.iter()
+        .zip(base_per_pool)
+        .map(|(amm, base)| match amm {
+            AMM::MiraV1(pool) if !pool.is_stable => {
+                pool.calculate_price_64_x_64(*base).map(Q64::to_f64_fast)
+            }
+            _ => amm.calculate_price(*base, amm.get_token_out(*base)),
+        })
+        .collect()
+}
+
+/// Which venue became the cheaper one to buy a token from after a price cross.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum CrossDirection {
+    /// The token is now cheaper on venue A than on venue B.
+    AtoBCheaper,
+    /// The token is now cheaper on venue B than on venue A.
+    BtoACheaper,
+}
+
+/// Calculates the price of `token` on `a` minus its price on `b`.
+///
+/// Both prices are denominated in the other token of each pool.
+pub fn price_spread(a: &AMM, b: &AMM, token: AssetId) -> Result<f64, ArithmeticError> {
+    let price_a = a.calculate_price(token, a.get_token_out(token))?;
+    let price_b = b.calculate_price(token, b.get_token_out(token))?;
+    Ok(price_a - price_b)
+}
+
+/// Compares the spot price of `base` on `a` and on `b` exactly, without going through `f64`.
+///
+/// Returns `Ordering::Less` when `base` is cheaper on `a`, or `None` if either pool has no
+/// [`AMM::spot_price_ratio`].
+pub fn cheaper_pool(a: &AMM, b: &AMM, base: AssetId) -> Option<Ordering> {
+    let (numerator_a, denominator_a) = a.spot_price_ratio(base)?;
+    let (numerator_b, denominator_b) = b.spot_price_ratio(base)?;
+
+    // Both products fit in `U512`, so the cross-multiplication cannot overflow
+    Some(
+        numerator_a
+            .full_mul(denominator_b)
+            .cmp(&numerator_b.full_mul(denominator_a)),
+    )
+}
+
+/// Detects whether the spread of `token` between `a` and `b` flipped sign since `prev_spread`.
+///
+/// Returns `None` when the sign did not flip, including when either spread is zero, or when a
+/// price cannot be calculated. Pass [`price_spread`] of the current state as the next
+/// `prev_spread`.
+pub fn price_cross(a: &AMM, b: &AMM, token: AssetId, prev_spread: f64) -> Option<CrossDirection> {
+    let spread = price_spread(a, b, token).ok()?;
+    if prev_spread > 0.0 && spread < 0.0 {
+        Some(CrossDirection::AtoBCheaper)
+    } else if prev_spread < 0.0 && spread > 0.0 {
+        Some(CrossDirection::BtoACheaper)
+    } else {
+        None
+    }
+}
+
+/// Calculates the range of prices of `token` on `pool_a` that cannot be arbitraged against
+/// `pool_b` once both pools' fees are paid.
+///
+/// Returns `None` if a fee is unknown or the price on `pool_b` cannot be calculated.
+pub fn arb_free_band(pool_a: &AMM, pool_b: &AMM, token: AssetId) -> Option<(f64, f64)> {
+    let price_b = pool_b
+        .calculate_price(token, pool_b.get_token_out(token))
+        .ok()?;
+    let kept = (1.0 - pool_a.fee_bps()?.as_fraction()) * (1.0 - pool_b.fee_bps()?.as_fraction());
+
+    Some((price_b * kept, price_b / kept))
+}
+
+/// Simulates swapping `amount_in` of `token_in` through each pool of `amms` in turn.
+///
+/// Returns the final output and the cumulative price impact in bps, fees included. Each hop's
+/// impact is measured against its spot price and the impacts compound, so a 1% and a 2% hop
+/// total 2.98%. Custom pools have no exact spot price and are rejected.
+pub fn simulate_path_with_impact(
+    amms: &[&AMM],
+    token_in: AssetId,
+    amount_in: U256,
+) -> Result<(U256, f64), SwapSimulationError> {
+    let (mut token, mut amount) = (token_in, amount_in);
+    let mut retained = 1.0;
+    for amm in amms {
+        let token_out = amm.get_token_out(token);
+        let (numerator, denominator) = amm
+            .spot_price_ratio(token)
+            .ok_or(SwapSimulationError::UnsupportedPool)?;
+        let state = amm.raw_state();
+        let (decimals_in, decimals_out) = if amm.tokens()[0] == token {
+            (state.decimals_0, state.decimals_1)
+        } else {
+            (state.decimals_1, state.decimals_0)
+        };
+
+        let amount_out = amm.simulate_swap(token, token_out, amount)?;
+        // The spot price is per whole token, so rescale it to raw amounts
+        let spot_out = amount
+            .checked_mul(numerator)
+            .and_then(|value| value.checked_mul(U256::from(10).pow(U256::from(decimals_out))))
+            .ok_or(SwapSimulationError::Overflow)?
+            / denominator
+                .checked_mul(U256::from(10).pow(U256::from(decimals_in)))
+                .ok_or(SwapSimulationError::Overflow)?;
+        if !spot_out.is_zero() {
+            retained *= u256_to_f64(amount_out) / u256_to_f64(spot_out);
+        }
+
+        (token, amount) = (token_out, amount_out);
+    }
+
+    Ok((amount, (1.0 - retained) * 10_000.0))
+}
+
+/// Calculates the impermanent loss of a constant product LP position as a fraction of holding.
+///
+/// Uses `2 * sqrt(r) / (1 + r) - 1` where `r = current_price / entry_price`, so the result is zero
+/// or negative.
+pub fn impermanent_loss(entry_price: f64, current_price: f64) -> f64 {
+    let ratio = current_price / entry_price;
+    2.0 * ratio.sqrt() / (1.0 + ratio) - 1.0
+}
+
+/// Calculates the shortfall of a fill against its expected output, in basis points.
+///
+/// Returns `(expected - actual) * 10000 / expected`, or zero when `actual >= expected`.
+pub fn realized_slippage_bps(expected_out: U256, actual_out: U256) -> u32 {
+    if actual_out >= expected_out {
+        return 0;
+    }
+
+    ((expected_out - actual_out) * U256::from(10_000) / expected_out).as_u32()
+}
+
+#[cfg(test)]
+mod tests {
+    use std::cmp::Ordering;
+
+    use fuels::types::{AssetId, ContractId, U256};
+
+    use super::{
+        arb_free_band, calculate_prices_batch, cheaper_pool, impermanent_loss, price_cross,
+        price_spread, realized_slippage_bps, simulate_path_with_impact, CrossDirection, EwmaPrice,
+        ReserveHistory,
+    };
+    use crate::amm::{mira::MiraV1, oxiswap::Oxiswap, AutomatedMarketMaker, AMM};
+
+    #[test]
+    fn test_ewma_price() {
+        let mut ewma = EwmaPrice::new(0.5).unwrap();
+
+        assert_eq!(ewma.update_price(1.0), 1.0);
+        assert_eq!(ewma.update_price(2.0), 1.5);
+        assert_eq!(ewma.update_price(3.0), 2.25);
+    }
+
+    #[test]
+    fn test_ewma_price_update_from_pool() {
+        let token_0 = AssetId::new([1; 32]);
+        let token_1 = AssetId::new([2; 32]);
+        let pool = |reserve_1| {
+            AMM::MiraV1(MiraV1::new(
+                ContractId::zeroed(),
+                (token_0, token_1, false),
+                token_0,
+                9,
+                token_1,
+                9,
+                1_000_000_000_000,
+                reserve_1,
+                (300, 50, 0, 0),
+                false,
+            ))
+        };
+        let mut ewma = EwmaPrice::new(0.5).unwrap();
+
+        let first = ewma.update(&pool(1_200_000_000_000), token_0).unwrap();
+        assert!((first - 1.2).abs() < 1e-9);
+        let second = ewma.update(&pool(1_600_000_000_000), token_0).unwrap();
+        assert!((second - 1.4).abs() < 1e-9);
+
+        // A pool without a price leaves the average untouched.
+        assert!(ewma.update(&pool(0), token_1).is_err());
+        assert_eq!(ewma.value, Some(second));
+    }
+
+    #[test]
+    fn test_ewma_price_rejects_invalid_alpha() {
+        assert!(EwmaPrice::new(0.0).is_err());
+        assert!(EwmaPrice::new(1.5).is_err());
+        assert!(EwmaPrice::new(f64::NAN).is_err());
+        assert!(EwmaPrice::new(1.0).is_ok());
+    }
+
+    #[test]
+    fn test_reserve_history_volatility() {
+        let mut history = ReserveHistory::new(4);
+        assert_eq!(history.realized_volatility(), 0.0);
+
+        // Prices 1, 2, 1, 2 give log returns of ln 2, -ln 2, ln 2.
+        for (block, reserve_1) in [(1, 1_000), (2, 2_000), (3, 1_000), (4, 2_000)] {
+            history.push(block, 1_000, reserve_1);
+        }
+        let expected = 2f64.ln() * (4.0f64 / 3.0).sqrt();
+        assert!((history.realized_volatility() - expected).abs() < 1e-12);
+
+        // Doubling prices evict the oldest samples and leave a constant return series.
+        history.push(5, 1_000, 4_000);
+        history.push(6, 1_000, 8_000);
+        assert_eq!(history.samples.len(), 4);
+        assert_eq!(history.samples.front().unwrap().0, 3);
+        assert!(history.realized_volatility().abs() < 1e-12);
+    }
+
+    #[test]
+    fn test_calculate_prices_batch_matches_calculate_price() {
+        let token_0 = AssetId::new([1; 32]);
+        let token_1 = AssetId::new([2; 32]);
+        let amms: Vec<AMM> = (1..=20u64)
+            .map(|i| {
+                AMM::MiraV1(MiraV1::new(
+                    ContractId::zeroed(),
+                    (token_0, token_1, false),
+                    token_0,
+                    9,
+                    token_1,
+                    6,
+                    1_000_000_000 * i,
+                    3_000_000 * i * i,
+                    (30, 5, 0, 0),
+                    false,
+                ))
+            })
+            .collect();
+        let bases: Vec<AssetId> = (0..amms.len())
+            .map(|i| if i % 2 == 0 { token_0 } else { token_1 })
+            .collect();
+
+        let prices = calculate_prices_batch(&amms, &bases);
+        for ((amm, base), price) in amms.iter().zip(&bases).zip(prices) {
+            let expected = amm
+                .calculate_price(*base, amm.get_token_out(*base))
+                .unwrap();
+            assert!((price.unwrap() - expected).abs() <= expected * 1e-12);
+        }
+    }
+
+    #[test]
+    fn test_price_cross() {
+        let token_0 = AssetId::new([1; 32]);
+        let token_1 = AssetId::new([2; 32]);
+        let pool = |reserve_1| {
+            AMM::MiraV1(MiraV1::new(
+                ContractId::zeroed(),
+                (token_0, token_1, false),
+                token_0,
+                9,
+                token_1,
+                9,
+                1_000_000_000,
+                reserve_1,
+                (300, 50, 0, 0),
+                false,
+            ))
+        };
+        let (cheap, expensive) = (pool(1_000_000_000), pool(1_200_000_000));
+
+        let spread = price_spread(&cheap, &expensive, token_0).unwrap();
+        assert!(spread < 0.0);
+        assert_eq!(price_cross(&cheap, &expensive, token_0, spread), None);
+
+        // The venues swap places, so the token becomes cheaper on B.
+        assert_eq!(
+            price_cross(&expensive, &cheap, token_0, spread),
+            Some(CrossDirection::BtoACheaper)
+        );
+        assert_eq!(
+            price_cross(&cheap, &expensive, token_0, -spread),
+            Some(CrossDirection::AtoBCheaper)
+        );
+    }
+
+    #[test]
+    fn test_cheaper_pool_exact() {
+        let token_0 = AssetId::new([1; 32]);
+        let token_1 = AssetId::new([2; 32]);
+        let pool = |reserve_1| {
+            AMM::MiraV1(MiraV1::new(
+                ContractId::zeroed(),
+                (token_0, token_1, false),
+                token_0,
+                9,
+                token_1,
+                9,
+                10_000_000_000_000_000_000,
+                reserve_1,
+                (300, 50, 0, 0),
+                false,
+            ))
+        };
+        let (a, b) = (
+            pool(10_000_000_000_000_000_000),
+            pool(10_000_000_000_000_000_001),
+        );
+
+        // The prices differ by 1e-19, which f64 cannot represent.
+        assert_eq!(
+            a.calculate_price(token_0, token_1).unwrap(),
+            b.calculate_price(token_0, token_1).unwrap()
+        );
+        assert_eq!(cheaper_pool(&a, &b, token_0), Some(Ordering::Less));
+        assert_eq!(cheaper_pool(&a, &b, token_1), Some(Ordering::Greater));
+        assert_eq!(cheaper_pool(&a, &a, token_0), Some(Ordering::Equal));
+    }
+
+    #[test]
+    fn test_arb_free_band() {
+        let token_0 = AssetId::new([1; 32]);
+        let token_1 = AssetId::new([2; 32]);
+        let pool = |fee| {
+            AMM::MiraV1(MiraV1::new(
+                ContractId::zeroed(),
+                (token_0, token_1, false),
+                token_0,
+                9,
+                token_1,
+                9,
+                1_000_000_000_000,
+                1_200_000_000_000,
+                (fee, 50, 0, 0),
+                false,
+            ))
+        };
+
+        // 30 and 100 bps leave 0.997 * 0.99 of the input after both swaps.
+        let (low, high) = arb_free_band(&pool(300), &pool(1_000), token_0).unwrap();
+        assert!((low - 1.2 * 0.98703).abs() < 1e-9);
+        assert!((high - 1.2 / 0.98703).abs() < 1e-9);
+
+        let (low, high) = arb_free_band(&pool(0), &pool(0), token_0).unwrap();
+        assert!((high - low).abs() < 1e-12);
+    }
+
+    #[test]
+    fn test_simulate_path_with_impact() {
+        let tokens: Vec<AssetId> = (1..=4).map(|i| AssetId::new([i; 32])).collect();
+        let pool = |hop: usize, reserve| {
+            AMM::Oxiswap(Oxiswap::new(
+                ContractId::zeroed(),
+                tokens[hop],
+                tokens[hop + 1],
+                reserve,
+                reserve,
+                300,
+            ))
+        };
+        let (deep_in, thin, deep_out) = (
+            pool(0, 1_000_000_000_000),
+            pool(1, 10_000_000),
+            pool(2, 1_000_000_000_000),
+        );
+        let amount_in = U256::from(1_000_000);
+
+        let (_, deep_impact) =
+            simulate_path_with_impact(&[&deep_in], tokens[0], amount_in).unwrap();
+        assert!((deep_impact - 30.01).abs() < 1e-6);
+
+        // The thin middle pool loses about 9.3% on top of the two 30 bps fees.
+        let (amount_out, impact) =
+            simulate_path_with_impact(&[&deep_in, &thin, &deep_out], tokens[0], amount_in).unwrap();
+        assert_eq!(amount_out, U256::from(901_422));
+        assert!((impact - 985.78).abs() < 1e-2);
+        assert!(impact > 30.0 * deep_impact);
+    }
+
+    #[test]
+    fn test_impermanent_loss() {
+        assert_eq!(impermanent_loss(1.0, 1.0), 0.0);
+        assert!((impermanent_loss(1.0, 2.0) + 0.057191).abs() < 1e-6);
+        assert!((impermanent_loss(2.0, 1.0) + 0.057191).abs() < 1e-6);
+        assert!((impermanent_loss(1.0, 5.0) + 0.254644).abs() < 1e-6);
+    }
+
+    #[test]
+    fn test_realized_slippage_bps() {
+        let expected = U256::from(1_000_000);
+        assert_eq!(realized_slippage_bps(expected, U256::from(997_000)), 30);
+        assert_eq!(realized_slippage_bps(expected, U256::zero()), 10_000);
+
+        // Fills at or above the expected output have no slippage.
+        assert_eq!(realized_slippage_bps(expected, expected), 0);
+        assert_eq!(realized_slippage_bps(expected, U256::from(1_010_000)), 0);
+    }
+}
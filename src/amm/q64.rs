@@ -0,0 +1,104 @@
+use std::ops::{Add, Div, Mul, Sub};
+
+use fuels::types::U256;
+use serde::{Deserialize, Serialize};
+
+use super::{
+    consts::U128_0X10000000000000000,
+    mira::{q64_to_f64, q64_to_f64_fast},
+};
+
+/// An unsigned Q64.64 fixed point number, i.e. the raw value divided by 2^64.
+#[derive(
+    Debug, Clone, Copy, Default, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize, Deserialize,
+)]
+pub struct Q64(pub u128);
+
+impl Q64 {
+    pub const ZERO: Q64 = Q64(0);
+    pub const ONE: Q64 = Q64(U128_0X10000000000000000);
+    pub const MAX: Q64 = Q64(u128::MAX);
+
+    /// Converts to f64 through `BigFloat`.
+    pub fn to_f64(self) -> f64 {
+        q64_to_f64(self.0)
+    }
+
+    /// Converts to f64 with plain float arithmetic, see [`q64_to_f64_fast`].
+    pub fn to_f64_fast(self) -> f64 {
+        q64_to_f64_fast(self.0)
+    }
+
+    /// Converts from f64, saturating at zero and at [`Q64::MAX`].
+    ///
+    /// NaN converts to zero.
+    pub fn from_f64(value: f64) -> Self {
+        Q64((value * U128_0X10000000000000000 as f64) as u128)
+    }
+}
+
+impl Add for Q64 {
+    type Output = Q64;
+
+    fn add(self, rhs: Q64) -> Q64 {
+        Q64(self.0 + rhs.0)
+    }
+}
+
+impl Sub for Q64 {
+    type Output = Q64;
+
+    fn sub(self, rhs: Q64) -> Q64 {
+        Q64(self.0 - rhs.0)
+    }
+}
+
+impl Mul for Q64 {
+    type Output = Q64;
+
+    /// Multiplies in `U256` and truncates the product back to 64 fractional bits.
+    fn mul(self, rhs: Q64) -> Q64 {
+        let product = (U256::from(self.0) * U256::from(rhs.0)) >> U256::from(64);
+        Q64(product.as_u128())
+    }
+}
+
+impl Div for Q64 {
+    type Output = Q64;
+
+    /// Divides in `U256`, truncating the quotient to 64 fractional bits.
+    fn div(self, rhs: Q64) -> Q64 {
+        let quotient = (U256::from(self.0) << U256::from(64)) / U256::from(rhs.0);
+        Q64(quotient.as_u128())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::Q64;
+
+    #[test]
+    fn test_q64_f64_round_trip() {
+        for value in [0.0, 1.0, 0.5, 1.2, 1e-9, 3.141592653589793, 1e12] {
+            let q64 = Q64::from_f64(value);
+            assert!((q64.to_f64() - value).abs() <= value * 1e-15 + 1e-18);
+            assert!((q64.to_f64_fast() - value).abs() <= value * 1e-15 + 1e-18);
+        }
+
+        assert_eq!(Q64::from_f64(1.0), Q64::ONE);
+        assert_eq!(Q64::from_f64(-1.0), Q64::ZERO);
+        assert_eq!(Q64::from_f64(f64::NAN), Q64::ZERO);
+        assert_eq!(Q64::from_f64(f64::INFINITY), Q64::MAX);
+    }
+
+    #[test]
+    fn test_q64_arithmetic() {
+        let (a, b) = (Q64::from_f64(1.5), Q64::from_f64(0.25));
+
+        assert_eq!(a + b, Q64::from_f64(1.75));
+        assert_eq!(a - b, Q64::from_f64(1.25));
+        assert_eq!(a * b, Q64::from_f64(0.375));
+        assert_eq!(a / b, Q64::from_f64(6.0));
+        assert_eq!(a * Q64::ONE, a);
+    }
+}
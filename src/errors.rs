@@ -7,6 +7,20 @@ pub enum AMMError {
     ContractError,
     #[error("Simulation Error")]
     SimulationError(#[from] Error),
+    #[error("Pool not found")]
+    PoolNotFound,
+    #[error("Wallet has no provider")]
+    MissingProvider,
+    #[error("Pool fee exceeds the configured maximum")]
+    FeeTooHigh,
+    #[error("Invalid pool id")]
+    InvalidPoolId,
+    #[error("Invalid pool state: {0}")]
+    InvalidState(&'static str),
+    #[error("Swap simulation error")]
+    Simulation(#[from] SwapSimulationError),
+    #[error("Arithmetic error")]
+    Arithmetic(#[from] ArithmeticError),
 }
 
 #[derive(Error, Debug)]
@@ -17,6 +31,16 @@ pub enum ArithmeticError {
     RoundingError,
     #[error("Y is zero")]
     YIsZero,
+    #[error("Smoothing factor must be in (0, 1]")]
+    InvalidSmoothingFactor,
+    #[error("Tokens are not a pair of the AMM")]
+    InvalidTokenPair,
+    #[error("Token decimals have not been populated")]
+    DecimalsUnknown,
+    #[error("Operation not supported by this pool type")]
+    UnsupportedPool,
+    #[error("Overflow Error")]
+    Overflow,
 }
 
 #[derive(Error, Debug)]
@@ -25,4 +49,48 @@ pub enum SwapSimulationError {
     Overflow,
     #[error("Division by zero error")]
     DivisionByZero,
+    #[error("Slippage exceeded")]
+    SlippageExceeded,
+    #[error("Operation not supported by this pool type")]
+    UnsupportedPool,
+    #[error("Pool is paused")]
+    PoolPaused,
+    #[error("Not enough liquidity to fill the swap")]
+    InsufficientLiquidity,
+    #[error("Token decimals have not been populated")]
+    DecimalsUnknown,
+}
+
+#[cfg(test)]
+mod tests {
+    use fuels::types::{AssetId, ContractId, U256};
+
+    use super::{AMMError, ArithmeticError, SwapSimulationError};
+    use crate::amm::{oxiswap::Oxiswap, AutomatedMarketMaker};
+
+    fn checked_swap(pool: &mut Oxiswap, amount_in: U256, min_out: U256) -> Result<U256, AMMError> {
+        let amount_out = pool.simulate_swap_mut_checked(pool.token_a, amount_in, min_out)?;
+        Ok(amount_out)
+    }
+
+    #[test]
+    fn test_local_errors_convert_to_amm_error() {
+        let mut pool = Oxiswap::new(
+            ContractId::zeroed(),
+            AssetId::new([1; 32]),
+            AssetId::new([2; 32]),
+            1_000_000,
+            1_000_000,
+            300,
+        );
+
+        assert!(matches!(
+            checked_swap(&mut pool, U256::from(1_000), U256::from(1_000)),
+            Err(AMMError::Simulation(SwapSimulationError::SlippageExceeded))
+        ));
+        assert!(matches!(
+            AMMError::from(ArithmeticError::DivisionByZero),
+            AMMError::Arithmetic(ArithmeticError::DivisionByZero)
+        ));
+    }
 }
@@ -1,3 +1,104 @@
 pub mod amm;
 pub mod errors;
 pub mod sync;
+#[cfg(feature = "tracing")]
+pub(crate) mod telemetry;
+
+use amm::{AutomatedMarketMaker, AMM};
+use errors::SwapSimulationError;
+use fuels::types::{AssetId, U256};
+
+/// Quotes the amount received for swapping `amount_in` of `token_in` in `amm`.
+///
+/// The output token is the other token of the pool.
+///
+/// ```
+/// use fuels::types::{AssetId, ContractId, U256};
+/// use fuels_amm_rs::{
+///     amm::{mira::MiraV1, oxiswap::Oxiswap, AMM},
+///     quote,
+/// };
+///
+/// let token_a = AssetId::new([1; 32]);
+/// let token_b = AssetId::new([2; 32]);
+/// let oxiswap = AMM::Oxiswap(Oxiswap::new(
+///     ContractId::zeroed(),
+///     token_a,
+///     token_b,
+///     1_000_000,
+///     1_000_000,
+///     300,
+/// ));
+/// let mira = AMM::MiraV1(MiraV1::new(
+///     ContractId::zeroed(),
+///     (token_a, token_b, false),
+///     token_a,
+///     9,
+///     token_b,
+///     9,
+///     1_000_000,
+///     1_000_000,
+///     (300, 50, 0, 0),
+///     false,
+/// ));
+///
+/// // Both pools charge 30 bps, so 1_000 in returns 996 out.
+/// assert_eq!(quote(&oxiswap, token_a, U256::from(1_000)).unwrap(), U256::from(996));
+/// assert_eq!(quote(&mira, token_b, U256::from(1_000)).unwrap(), U256::from(996));
+/// ```
+pub fn quote(amm: &AMM, token_in: AssetId, amount_in: U256) -> Result<U256, SwapSimulationError> {
+    amm.simulate_swap(token_in, amm.get_token_out(token_in), amount_in)
+}
+
+/// Quotes every `(amm, token_in, amount_in)` request, returning the results in order.
+pub fn batch_quote(requests: &[(&AMM, AssetId, U256)]) -> Vec<Result<U256, SwapSimulationError>> {
+    requests
+        .iter()
+        .map(|(amm, token_in, amount_in)| quote(amm, *token_in, *amount_in))
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use fuels::types::{AssetId, ContractId, U256};
+
+    use super::{batch_quote, quote};
+    use crate::amm::{mira::MiraV1, oxiswap::oxiswap_amm, AMM};
+
+    #[test]
+    fn test_batch_quote() {
+        let token_a = AssetId::new([1; 32]);
+        let token_b = AssetId::new([2; 32]);
+        let oxiswap = oxiswap_amm(
+            ContractId::zeroed(),
+            token_a,
+            token_b,
+            1_000_000,
+            1_000_000,
+            300,
+        );
+        let mira = AMM::MiraV1(MiraV1::new(
+            ContractId::zeroed(),
+            (token_a, token_b, true),
+            token_a,
+            9,
+            token_b,
+            9,
+            1_000_000_000,
+            1_000_000_000,
+            (300, 50, 0, 0),
+            true,
+        ));
+        let requests = [
+            (&oxiswap, token_a, U256::from(1_000)),
+            (&mira, token_b, U256::from(5_000_000)),
+            (&oxiswap, token_b, U256::zero()),
+        ];
+
+        let quotes = batch_quote(&requests);
+        assert_eq!(quotes.len(), requests.len());
+        for ((amm, token_in, amount_in), result) in requests.iter().zip(quotes) {
+            assert_eq!(result.unwrap(), quote(amm, *token_in, *amount_in).unwrap());
+        }
+    }
+}
@@ -1 +1,7 @@
+pub mod quarantine;
+#[cfg(feature = "stream")]
+pub mod stream;
 
+pub use quarantine::{active_amms, sync_active, PoolStatus, TrackedPool};
+#[cfg(feature = "stream")]
+pub use stream::spawn_price_stream;
@@ -0,0 +1,234 @@
+use fuels::{accounts::wallet::Wallet, types::ContractId};
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    amm::{AutomatedMarketMaker, AMM},
+    errors::AMMError,
+};
+
+/// Whether a pool takes part in batch operations.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub enum PoolStatus {
+    #[default]
+    Active,
+    /// The pool's state failed validation, for the given reason.
+    Quarantined(String),
+}
+
+/// A pool tracked across syncs, quarantined once its state stops validating.
+#[derive(Debug, Clone)]
+pub struct TrackedPool {
+    pub amm: AMM,
+    pub status: PoolStatus,
+}
+
+impl TrackedPool {
+    /// Tracks `amm` as an active pool.
+    pub fn new(amm: AMM) -> Self {
+        Self {
+            amm,
+            status: PoolStatus::Active,
+        }
+    }
+
+    /// Returns whether the pool takes part in batch operations.
+    pub fn is_active(&self) -> bool {
+        self.status == PoolStatus::Active
+    }
+
+    /// Syncs the pool, quarantining it when the synced state fails [`AMM::validate_state`].
+    ///
+    /// Errors from the sync itself are returned and leave the status unchanged.
+    pub async fn sync(&mut self, wallet: Wallet) -> Result<(), AMMError> {
+        self.amm.sync(wallet).await?;
+        if let Err(err) = self.amm.validate_state() {
+            self.status = PoolStatus::Quarantined(err.to_string());
+        }
+
+        Ok(())
+    }
+
+    /// Returns a quarantined pool to batch operations.
+    pub fn reactivate(&mut self) {
+        self.status = PoolStatus::Active;
+    }
+}
+
+/// Syncs every active pool of `pools`, skipping quarantined ones.
+///
+/// Returns the address and error of every pool that failed to sync.
+pub async fn sync_active(pools: &mut [TrackedPool], wallet: Wallet) -> Vec<(ContractId, AMMError)> {
+    let mut failures = vec![];
+    for pool in pools.iter_mut().filter(|pool| pool.is_active()) {
+        if let Err(err) = pool.sync(wallet.clone()).await {
+            failures.push((pool.amm.address(), err));
+        }
+    }
+
+    failures
+}
+
+/// Returns the pools of `pools` that are not quarantined.
+pub fn active_amms(pools: &[TrackedPool]) -> Vec<&AMM> {
+    pools
+        .iter()
+        .filter(|pool| pool.is_active())
+        .map(|pool| &pool.amm)
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::{
+        atomic::{AtomicUsize, Ordering},
+        Arc,
+    };
+
+    use async_trait::async_trait;
+    use fuels::{
+        accounts::wallet::Wallet,
+        types::{bech32::Bech32Address, AssetId, ContractId, U256},
+    };
+
+    use super::{active_amms, sync_active, PoolStatus, TrackedPool};
+    use crate::{
+        amm::{AutomatedMarketMaker, RawState, AMM},
+        errors::{AMMError, ArithmeticError, SwapSimulationError},
+    };
+
+    /// A pool whose sync applies `synced_fee_bps`, counting the syncs.
+    #[derive(Debug, Clone)]
+    struct DriftingPool {
+        address: ContractId,
+        fee_bps: u32,
+        synced_fee_bps: u32,
+        syncs: Arc<AtomicUsize>,
+    }
+
+    #[async_trait]
+    impl AutomatedMarketMaker for DriftingPool {
+        fn address(&self) -> ContractId {
+            self.address
+        }
+
+        async fn sync(&mut self, _wallet: Wallet) -> Result<(), AMMError> {
+            self.fee_bps = self.synced_fee_bps;
+            self.syncs.fetch_add(1, Ordering::Relaxed);
+            Ok(())
+        }
+
+        fn tokens(&self) -> Vec<AssetId> {
+            vec![AssetId::new([1; 32]), AssetId::new([2; 32])]
+        }
+
+        fn calculate_price(
+            &self,
+            _base_token: AssetId,
+            _quote_token: AssetId,
+        ) -> Result<f64, ArithmeticError> {
+            Ok(1.0)
+        }
+
+        async fn populate_data(
+            &mut self,
+            _block_number: Option<u64>,
+            _wallet: Wallet,
+        ) -> Result<(), AMMError> {
+            Ok(())
+        }
+
+        fn simulate_swap(
+            &self,
+            _base_token: AssetId,
+            _quote_token: AssetId,
+            amount_in: U256,
+        ) -> Result<U256, SwapSimulationError> {
+            Ok(amount_in)
+        }
+
+        fn simulate_swap_mut(
+            &mut self,
+            _base_token: AssetId,
+            _quote_token: AssetId,
+            amount_in: U256,
+        ) -> Result<U256, SwapSimulationError> {
+            Ok(amount_in)
+        }
+
+        fn get_token_out(&self, token_in: AssetId) -> AssetId {
+            token_in
+        }
+
+        fn supports_swap(&self, _token_in: AssetId, _token_out: AssetId) -> bool {
+            true
+        }
+
+        fn reserves(&self) -> (u64, u64) {
+            (1_000, 1_000)
+        }
+
+        fn normalized_reserves(&self) -> (U256, U256) {
+            (U256::from(1_000), U256::from(1_000))
+        }
+
+        fn raw_state(&self) -> RawState {
+            RawState {
+                reserve_0: U256::from(1_000),
+                reserve_1: U256::from(1_000),
+                decimals_0: 9,
+                decimals_1: 9,
+                fee_bps: self.fee_bps,
+                is_stable: false,
+            }
+        }
+
+        fn has_liquidity(&self) -> bool {
+            true
+        }
+
+        fn lp_asset_id(&self) -> Option<AssetId> {
+            None
+        }
+    }
+
+    #[test]
+    fn test_invalid_sync_quarantines_pool() {
+        let syncs = [Arc::new(AtomicUsize::new(0)), Arc::new(AtomicUsize::new(0))];
+        let pool = |index: usize, synced_fee_bps| {
+            TrackedPool::new(AMM::Custom(Box::new(DriftingPool {
+                address: ContractId::new([index as u8; 32]),
+                fee_bps: 30,
+                synced_fee_bps,
+                syncs: syncs[index].clone(),
+            })))
+        };
+        let sync_counts = || {
+            (
+                syncs[0].load(Ordering::Relaxed),
+                syncs[1].load(Ordering::Relaxed),
+            )
+        };
+        // The second pool reads back a 100% fee on its next sync.
+        let mut pools = vec![pool(0, 30), pool(1, 10_000)];
+        let wallet = Wallet::from_address(Bech32Address::default(), None);
+        let runtime = tokio::runtime::Builder::new_current_thread()
+            .build()
+            .unwrap();
+
+        assert!(runtime
+            .block_on(sync_active(&mut pools, wallet.clone()))
+            .is_empty());
+        assert!(pools[0].is_active());
+        assert!(matches!(pools[1].status, PoolStatus::Quarantined(_)));
+        assert_eq!(active_amms(&pools).len(), 1);
+
+        // Quarantined pools are no longer synced until reactivated.
+        runtime.block_on(sync_active(&mut pools, wallet.clone()));
+        assert_eq!(sync_counts(), (2, 1));
+
+        pools[1].reactivate();
+        runtime.block_on(sync_active(&mut pools, wallet));
+        assert_eq!(sync_counts(), (3, 2));
+        assert!(!pools[1].is_active());
+    }
+}
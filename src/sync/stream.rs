@@ -0,0 +1,195 @@
+use std::time::Duration;
+
+use fuels::{
+    accounts::wallet::Wallet,
+    types::{AssetId, ContractId},
+};
+use tokio::{sync::mpsc, task::JoinHandle};
+
+use crate::amm::{AutomatedMarketMaker, AMM};
+
+/// Spawns a task that syncs `amms` every `interval` and emits `(address, price)` whenever the
+/// price of `base` in a pool changes.
+///
+/// Pools that do not hold `base`, or that fail to sync or price, are skipped for that round. The
+/// task exits once the receiver is dropped.
+pub fn spawn_price_stream(
+    mut amms: Vec<AMM>,
+    base: AssetId,
+    wallet: Wallet,
+    interval: Duration,
+) -> (JoinHandle<()>, mpsc::Receiver<(ContractId, f64)>) {
+    let (sender, receiver) = mpsc::channel(amms.len().max(1));
+
+    let handle = tokio::spawn(async move {
+        let mut last_prices: Vec<Option<f64>> = vec![None; amms.len()];
+
+        while !sender.is_closed() {
+            for (amm, last_price) in amms.iter_mut().zip(last_prices.iter_mut()) {
+                if !amm.tokens().contains(&base) || amm.sync(wallet.clone()).await.is_err() {
+                    continue;
+                }
+
+                let Ok(price) = amm.calculate_price(base, amm.get_token_out(base)) else {
+                    continue;
+                };
+
+                if *last_price == Some(price) {
+                    continue;
+                }
+                *last_price = Some(price);
+
+                if sender.send((amm.address(), price)).await.is_err() {
+                    return;
+                }
+            }
+
+            tokio::time::sleep(interval).await;
+        }
+    });
+
+    (handle, receiver)
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+
+    use async_trait::async_trait;
+    use fuels::{
+        accounts::wallet::Wallet,
+        types::{bech32::Bech32Address, AssetId, ContractId, U256},
+    };
+
+    use super::spawn_price_stream;
+    use crate::{
+        amm::{AutomatedMarketMaker, RawState, AMM},
+        errors::{AMMError, ArithmeticError, SwapSimulationError},
+    };
+
+    /// A pool with fixed reserves whose sync is a no-op.
+    #[derive(Debug, Clone)]
+    struct FixedPool {
+        token_a: AssetId,
+        token_b: AssetId,
+    }
+
+    #[async_trait]
+    impl AutomatedMarketMaker for FixedPool {
+        fn address(&self) -> ContractId {
+            ContractId::new([9; 32])
+        }
+
+        async fn sync(&mut self, _wallet: Wallet) -> Result<(), AMMError> {
+            Ok(())
+        }
+
+        fn tokens(&self) -> Vec<AssetId> {
+            vec![self.token_a, self.token_b]
+        }
+
+        fn calculate_price(
+            &self,
+            _base_token: AssetId,
+            _quote_token: AssetId,
+        ) -> Result<f64, ArithmeticError> {
+            Ok(2.0)
+        }
+
+        async fn populate_data(
+            &mut self,
+            _block_number: Option<u64>,
+            _wallet: Wallet,
+        ) -> Result<(), AMMError> {
+            Ok(())
+        }
+
+        fn simulate_swap(
+            &self,
+            _base_token: AssetId,
+            _quote_token: AssetId,
+            amount_in: U256,
+        ) -> Result<U256, SwapSimulationError> {
+            Ok(amount_in * 2)
+        }
+
+        fn simulate_swap_mut(
+            &mut self,
+            base_token: AssetId,
+            quote_token: AssetId,
+            amount_in: U256,
+        ) -> Result<U256, SwapSimulationError> {
+            self.simulate_swap(base_token, quote_token, amount_in)
+        }
+
+        fn get_token_out(&self, token_in: AssetId) -> AssetId {
+            if token_in == self.token_a {
+                self.token_b
+            } else {
+                self.token_a
+            }
+        }
+
+        fn supports_swap(&self, token_in: AssetId, token_out: AssetId) -> bool {
+            self.tokens().contains(&token_in)
+                && self.tokens().contains(&token_out)
+                && token_in != token_out
+        }
+
+        fn reserves(&self) -> (u64, u64) {
+            (1_000, 2_000)
+        }
+
+        fn normalized_reserves(&self) -> (U256, U256) {
+            (U256::from(1_000), U256::from(2_000))
+        }
+
+        fn raw_state(&self) -> RawState {
+            RawState {
+                reserve_0: U256::from(1_000),
+                reserve_1: U256::from(2_000),
+                decimals_0: 18,
+                decimals_1: 18,
+                fee_bps: 0,
+                is_stable: false,
+            }
+        }
+
+        fn has_liquidity(&self) -> bool {
+            true
+        }
+
+        fn lp_asset_id(&self) -> Option<AssetId> {
+            None
+        }
+    }
+
+    #[test]
+    fn test_spawn_price_stream() {
+        let runtime = tokio::runtime::Builder::new_current_thread()
+            .enable_time()
+            .build()
+            .unwrap();
+
+        runtime.block_on(async {
+            let base = AssetId::new([1; 32]);
+            let pool = FixedPool {
+                token_a: base,
+                token_b: AssetId::new([2; 32]),
+            };
+            let wallet = Wallet::from_address(Bech32Address::default(), None);
+
+            let (handle, mut receiver) = spawn_price_stream(
+                vec![AMM::Custom(Box::new(pool))],
+                base,
+                wallet,
+                Duration::from_millis(5),
+            );
+
+            assert_eq!(receiver.recv().await, Some((ContractId::new([9; 32]), 2.0)));
+
+            drop(receiver);
+            handle.await.unwrap();
+        });
+    }
+}
@@ -0,0 +1,21 @@
+use std::time::Instant;
+
+/// Emits a debug event with the time elapsed since it was started once dropped.
+///
+/// Held for the duration of an instrumented call so early returns are timed as well.
+pub(crate) struct Elapsed(Instant);
+
+impl Elapsed {
+    pub(crate) fn start() -> Self {
+        Self(Instant::now())
+    }
+}
+
+impl Drop for Elapsed {
+    fn drop(&mut self) {
+        tracing::debug!(
+            elapsed_ms = self.0.elapsed().as_millis() as u64,
+            "call finished"
+        );
+    }
+}